@@ -0,0 +1,272 @@
+use token::Token;
+use token_type::Type;
+use literal::Literal;
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Expr {
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+    },
+    // the token is kept so anything about the value, like a runtime
+    // error, can say where in the source it was written
+    Literal {
+        value: Literal,
+        token: Token,
+    },
+    Grouping(Box<Expr>),
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+    },
+}
+
+// takes out the parentheses that don't change how an expression reads,
+// so `(1)` becomes `1` but `(1 + 2) * 3` keeps its group.
+// the parser keeps every Grouping so that this is up to whoever asks.
+pub fn strip_redundant_parens(expr: Expr) -> Expr {
+    strip(expr, LOWEST)
+}
+
+// `needed` is how tightly the spot `expr` sits in has to bind
+fn strip(expr: Expr, needed: u8) -> Expr {
+    match expr {
+        Expr::Grouping(inner) => {
+            let inner = strip(*inner, LOWEST);
+            if binding(&inner) >= needed {
+                inner
+            } else {
+                Expr::Grouping(Box::new(inner))
+            }
+        },
+        Expr::Binary { left, operator, right } => {
+            let (mut left_needs, right_needs) = operand_bindings(operator.token_type);
+            // `(1 < 2) < 3` without its group reads as a chained comparison,
+            // which the parser won't take, so a comparison on either side keeps it
+            if Type::COMPARISONS.contains(&operator.token_type) {
+                left_needs = right_needs;
+            }
+            Expr::Binary {
+                left: Box::new(strip(*left, left_needs)),
+                operator,
+                right: Box::new(strip(*right, right_needs)),
+            }
+        },
+        Expr::Unary { operator, right } => Expr::Unary {
+            operator,
+            right: Box::new(strip(*right, UNARY)),
+        },
+        literal @ Expr::Literal { .. } => literal,
+    }
+}
+
+// how tightly each kind of expression binds, following the parser's rules
+const LOWEST: u8 = 0;
+const UNARY: u8 = 6;
+const PRIMARY: u8 = 7;
+
+fn binding(expr: &Expr) -> u8 {
+    match *expr {
+        Expr::Binary { ref operator, .. } => binary_binding(operator.token_type),
+        Expr::Unary { .. } => UNARY,
+        Expr::Literal { .. } | Expr::Grouping(_) => PRIMARY,
+    }
+}
+
+fn binary_binding(tt: Type) -> u8 {
+    // nothing the parser makes, so assume the worst
+    binary_operator(tt).map(|(power, _)| power).unwrap_or(LOWEST)
+}
+
+// which way a run of operators of the same precedence groups:
+// `1 - 2 - 3` is `(- (- 1 2) 3)` because `-` is Left
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+// every binary operator, loosest first, with how tightly it binds.
+// the parser reads expressions off this, so adding an operator
+// is adding a row.
+const BINARY_OPERATORS: &[(Type, u8, Associativity)] = &[
+    (Type::QuestionQuestion, 1, Associativity::Left),
+    (Type::BangEqual, 2, Associativity::Left),
+    (Type::EqualEqual, 2, Associativity::Left),
+    (Type::Greater, 3, Associativity::Left),
+    (Type::GreaterEqual, 3, Associativity::Left),
+    (Type::Less, 3, Associativity::Left),
+    (Type::LessEqual, 3, Associativity::Left),
+    (Type::Minus, 4, Associativity::Left),
+    (Type::Plus, 4, Associativity::Left),
+    (Type::Slash, 5, Associativity::Left),
+    (Type::Star, 5, Associativity::Left),
+    (Type::Percent, 5, Associativity::Left),
+];
+
+pub fn binary_operator(tt: Type) -> Option<(u8, Associativity)> {
+    BINARY_OPERATORS.iter()
+        .find(|&&(operator, _, _)| operator == tt)
+        .map(|&(_, power, associativity)| (power, associativity))
+}
+
+// how tightly each side of `tt` has to bind to do without a group.
+// the side a run groups toward can hold the same operator again,
+// the other needs something tighter: `1 - (2 - 3)` keeps its parens.
+pub fn operand_bindings(tt: Type) -> (u8, u8) {
+    match binary_operator(tt) {
+        Some((power, Associativity::Right)) => (power + 1, power),
+        Some((power, Associativity::Left)) => (power, power + 1),
+        None => (LOWEST, LOWEST + 1),
+    }
+}
+
+// renders a tree with every node parenthesized, like `(* (- 5) (group 3))`,
+// so there's no doubt about how the parser read something
+pub struct AstPrinter;
+
+impl AstPrinter {
+    pub fn print(&self, expr: &Expr) -> String {
+        match *expr {
+            Expr::Binary { ref left, ref operator, ref right } =>
+                self.parenthesize(&operator.lexeme, &[left, right]),
+            Expr::Unary { ref operator, ref right } =>
+                self.parenthesize(&operator.lexeme, &[right]),
+            Expr::Literal { ref value, .. } => value.to_string(),
+            Expr::Grouping(ref inner) => self.parenthesize("group", &[inner]),
+        }
+    }
+
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut out = format!("({}", name);
+        for expr in exprs {
+            out.push(' ');
+            out.push_str(&self.print(expr));
+        }
+        out.push(')');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::Parser;
+    use scanner::scan;
+
+    fn parse(source: &str) -> Expr {
+        Parser::new(scan(source).unwrap()).parse_expression().unwrap()
+    }
+
+    fn stripped(source: &str) -> String {
+        AstPrinter.print(&strip_redundant_parens(parse(source)))
+    }
+
+    #[test]
+    fn strips_parens_around_a_single_value() {
+        assert_eq!(stripped("(1)"), "1");
+        assert_eq!(stripped("((1))"), "1");
+        assert_eq!(stripped("-(1)"), "(- 1)");
+    }
+
+    #[test]
+    fn keeps_parens_that_change_precedence() {
+        assert_eq!(stripped("(1 + 2) * 3"), "(* (group (+ 1 2)) 3)");
+        assert_eq!(stripped("-(1 + 2)"), "(- (group (+ 1 2)))");
+        assert_eq!(stripped("1 == (2 == 3)"), "(== 1 (group (== 2 3)))");
+        assert_eq!(stripped("(nil ?? 1) == 2"), "(== (group (?? nil 1)) 2)");
+    }
+
+    #[test]
+    fn strips_parens_that_repeat_precedence() {
+        assert_eq!(stripped("1 + (2 * 3)"), "(+ 1 (* 2 3))");
+        assert_eq!(stripped("(1 * 2) + 3"), "(+ (* 1 2) 3)");
+        assert_eq!(stripped("(1 - 2) - 3"), "(- (- 1 2) 3)");
+    }
+
+    #[test]
+    fn keeps_parens_that_change_associativity() {
+        assert_eq!(stripped("1 - (2 - 3)"), "(- 1 (group (- 2 3)))");
+    }
+
+    #[test]
+    fn keeps_parens_that_stop_comparisons_chaining() {
+        assert_eq!(stripped("(1 < 2) < 3"), "(< (group (< 1 2)) 3)");
+        assert_eq!(stripped("1 >= (2 < 3)"), "(>= 1 (group (< 2 3)))");
+        assert_eq!(stripped("((1 < 2)) < 3"), "(< (group (< 1 2)) 3)");
+        assert_eq!(stripped("(1 + 2) < 3"), "(< (+ 1 2) 3)");
+    }
+
+    #[test]
+    fn strips_inside_kept_parens() {
+        assert_eq!(stripped("((1) + 2) * 3"), "(* (group (+ 1 2)) 3)");
+    }
+
+    fn token(tt: Type, lexeme: &str) -> Token {
+        Token::synthetic(tt, lexeme, (1, 0))
+    }
+
+    fn literal(value: Literal) -> Expr {
+        let tt = match value {
+            Literal::String(_) => Type::String,
+            Literal::Boolean(true) => Type::True,
+            Literal::Boolean(false) => Type::False,
+            Literal::Nil => Type::Nil,
+            _ => Type::Number,
+        };
+        Expr::Literal { token: token(tt, &value.to_string()), value }
+    }
+
+    #[test]
+    fn prints_a_known_tree() {
+        let expr = Expr::Binary {
+            left: Box::new(Expr::Unary {
+                operator: token(Type::Minus, "-"),
+                right: Box::new(literal(Literal::Integer(5))),
+            }),
+            operator: token(Type::Star, "*"),
+            right: Box::new(Expr::Grouping(Box::new(literal(Literal::Number(3.5))))),
+        };
+
+        assert_eq!(AstPrinter.print(&expr), "(* (- 5) (group 3.5))");
+    }
+
+    #[test]
+    fn prints_values_the_way_literals_display() {
+        let values = vec![
+            (Literal::Nil, "nil"),
+            (Literal::Boolean(true), "true"),
+            (Literal::String("hi there".to_owned()), "hi there"),
+            (Literal::Number(2.0), "2.0"),
+        ];
+
+        for (value, printed) in values {
+            assert_eq!(AstPrinter.print(&literal(value)), printed);
+        }
+    }
+
+    #[test]
+    fn prints_nested_groups() {
+        let expr = Expr::Grouping(Box::new(Expr::Grouping(Box::new(Expr::Binary {
+            left: Box::new(literal(Literal::Integer(1))),
+            operator: token(Type::EqualEqual, "=="),
+            right: Box::new(Expr::Unary {
+                operator: token(Type::Bang, "!"),
+                right: Box::new(literal(Literal::Boolean(false))),
+            }),
+        }))));
+
+        assert_eq!(AstPrinter.print(&expr), "(group (group (== 1 (! false))))");
+    }
+}
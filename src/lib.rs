@@ -0,0 +1,12 @@
+extern crate unicode_xid;
+#[cfg(feature = "serde")]
+extern crate serde;
+
+pub mod token_type;
+pub mod literal;
+pub mod token;
+pub mod scanner;
+pub mod tokens;
+pub mod metrics;
+pub mod ast;
+pub mod parser;
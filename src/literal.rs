@@ -1,18 +1,52 @@
 use std::str::FromStr;
-use std::string::ToString;
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fmt::{Display,Formatter,Result as FResult};
 
-#[derive(Debug)]
+// equality is structural, so `Integer(1)` and `Number(1.0)` differ,
+// and numbers follow ieee rules, so `NaN` isn't equal to itself.
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Literal {
     String(String),
     Number(f64),
+    Integer(i64),
     Boolean(bool),
+    Char(char),
+    Nil,
 }
 
 impl Literal {
     pub fn parse(s: String) -> ParseResult {
-        Self::from_str(&*s)
+        Self::from_str(&s)
+    }
+
+    // for when it's already known what kind of literal `s` should be
+    pub fn number(s: &str) -> ParseResult {
+        parse_number(s)
+    }
+
+    pub fn string(s: &str) -> ParseResult {
+        parse_string(s)
+    }
+
+    pub fn character(s: &str) -> ParseResult {
+        parse_char(s)
+    }
+
+    // the inside of a string, already without its quotes
+    pub fn unescaped(s: &str) -> ParseResult {
+        unescape(s).map(Literal::String).map_err(|message| ParseLiteralErr {
+            literal: s.to_owned(),
+            message,
+        })
+    }
+
+    // lox only counts `false` and `nil` as false.
+    // unlike some languages, `0` and `""` are true.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(*self, Literal::Boolean(false) | Literal::Nil)
     }
 }
 
@@ -22,51 +56,185 @@ impl FromStr for Literal {
     type Err = ParseLiteralErr;
 
     fn from_str(s: &str) -> ParseResult {
+        // anything starting like a number should report why it isn't one,
+        // not why it isn't a char
+        let numeric = s.starts_with(|c: char| c.is_ascii_digit() || c == '.');
         parse_bool(s)
+            .or_else(|_| parse_nil(s))
             .or_else(|_| parse_number(s))
-            .or_else(|_| parse_string(s))
+            .or_else(|e| if numeric { Err(e) } else { parse_string(s) })
+            .or_else(|e| if numeric { Err(e) } else { parse_char(s) })
     }
 }
 
 fn parse_bool(s: &str) -> ParseResult {
     bool::from_str(s)
-        .map(|b| Literal::Boolean(b))
+        .map(Literal::Boolean)
         .map_err(|e| ParseLiteralErr {
             literal: s.to_owned(),
             message: format!("{}", e),
         })
 }
 
-fn parse_string(s: &str) -> ParseResult {
-    // shouldn't need to be here....?
-    if !(s.starts_with("\"") && s.ends_with("\"")) {
-        return Err(ParseLiteralErr {
+fn parse_nil(s: &str) -> ParseResult {
+    if s == "nil" {
+        Ok(Literal::Nil)
+    } else {
+        Err(ParseLiteralErr {
             literal: s.to_owned(),
-            message: "Incorrectly formatted string!".to_owned(),
+            message: "Not nil!".to_owned(),
         })
     }
+}
 
-    s.get(1..s.len() - 1).map(String::from).map(Literal::String).ok_or(ParseLiteralErr {
+fn parse_string(s: &str) -> ParseResult {
+    let err = |message: String| ParseLiteralErr {
         literal: s.to_owned(),
-        message: "Empty string!".to_owned(),
-    })
+        message,
+    };
+
+    // a raw string like r"C:\temp" keeps everything between the quotes as written
+    let raw = s.starts_with("r\"");
+    let quoted = if raw { &s[1..] } else { s };
+    // shouldn't need to be here....?
+    if !(quoted.starts_with("\"") && quoted.ends_with("\"")) {
+        return Err(err("Incorrectly formatted string!".to_owned()))
+    }
+
+    let contents = quoted.get(1..quoted.len() - 1).ok_or_else(|| err("Empty string!".to_owned()))?;
+    if raw {
+        Ok(Literal::String(contents.to_owned()))
+    } else {
+        unescape(contents).map(Literal::String).map_err(err)
+    }
 }
 
+fn unescape(s: &str) -> Result<String, String> {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue
+        }
+
+        unescaped.push(match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            // `\$` keeps a `${` from starting an interpolation
+            Some(escaped @ '\\') | Some(escaped @ '"') | Some(escaped @ '$') => escaped,
+            Some(escaped) => return Err(format!("Unknown escape \\{}!", escaped)),
+            None => return Err("Unfinished escape!".to_owned()),
+        });
+    }
+    Ok(unescaped)
+}
+
+fn parse_char(s: &str) -> ParseResult {
+    let err = |message: &str| ParseLiteralErr {
+        literal: s.to_owned(),
+        message: message.to_owned(),
+    };
+
+    if s.len() < 2 || !(s.starts_with('\'') && s.ends_with('\'')) {
+        return Err(err("Incorrectly formatted character!"))
+    }
+
+    let mut chars = s[1..s.len() - 1].chars();
+    let c = match (chars.next(), chars.next(), chars.next()) {
+        (Some('\\'), Some(escaped), None) => match escaped {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' | '\'' | '"' => escaped,
+            _ => return Err(err("Unknown escape!")),
+        },
+        (Some(c), None, None) => c,
+        _ => return Err(err("Character literals hold exactly one character!")),
+    };
+
+    Ok(Literal::Char(c))
+}
+
+// whole numbers are Integers, unless they're too big for one,
+// and anything with a fraction or exponent is a Number
 fn parse_number(s: &str) -> ParseResult {
-    f64::from_str(s)
-        .map(|n| Literal::Number(n))
-        .map_err(|e| ParseLiteralErr {
-            literal: s.to_owned(),
-            message: format!("{}", e),
-        })
+    let n = match radix_digits(s) {
+        Some((digits, radix)) => i64::from_str_radix(digits, radix)
+            .map(Literal::Integer)
+            .map_err(|e| e.to_string()),
+        None if !s.contains(&['.', 'e', 'E'][..]) => i64::from_str(s)
+            .map(Literal::Integer)
+            .or_else(|_| parse_float(s)),
+        None => parse_float(s),
+    };
+
+    n.map_err(|message| ParseLiteralErr {
+        literal: s.to_owned(),
+        message,
+    })
 }
 
-impl ToString for Literal {
-    fn to_string(&self) -> String {
+fn parse_float(s: &str) -> Result<Literal, String> {
+    let n = f64::from_str(s).map_err(|e| e.to_string())?;
+    // something like `1e400` is too big for an f64 and comes out infinite,
+    // which is never what whoever wrote the digits meant.
+    // a spelled out `inf` is left alone.
+    if n.is_finite() || !s.contains(|c: char| c.is_ascii_digit()) {
+        Ok(Literal::Number(n))
+    } else {
+        Err("number literal too large".to_owned())
+    }
+}
+
+// integers written in another base, like `0xff` or `0b101`
+fn radix_digits(s: &str) -> Option<(&str, u32)> {
+    if s.starts_with("0x") || s.starts_with("0X") {
+        Some((&s[2..], 16))
+    } else if s.starts_with("0b") || s.starts_with("0B") {
+        Some((&s[2..], 2))
+    } else {
+        None
+    }
+}
+
+// only literals of the same kind have an order
+impl PartialOrd for Literal {
+    fn partial_cmp(&self, other: &Literal) -> Option<Ordering> {
+        match (self, other) {
+            (Literal::String(a), Literal::String(b)) => a.partial_cmp(b),
+            (Literal::Number(a), Literal::Number(b)) => a.partial_cmp(b),
+            (Literal::Integer(a), Literal::Integer(b)) => a.partial_cmp(b),
+            (Literal::Boolean(a), Literal::Boolean(b)) => a.partial_cmp(b),
+            (Literal::Char(a), Literal::Char(b)) => a.partial_cmp(b),
+            (Literal::Nil, Literal::Nil) => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
         match *self {
-            Literal::String(ref s) => s.clone(),
-            Literal::Number(n) => n.to_string(),
-            Literal::Boolean(b) => b.to_string(),
+            Literal::String(ref s) => write!(f, "{}", s),
+            // debug formatting always keeps a fraction or exponent,
+            // so `Number(5.0)` shows as `5.0` and not like `Integer(5)`
+            Literal::Number(n) => write!(f, "{:?}", n),
+            Literal::Integer(n) => write!(f, "{}", n),
+            Literal::Boolean(b) => write!(f, "{}", b),
+            // quoted and escaped the way it's written, so it parses back
+            Literal::Char(c) => match c {
+                '\n' => write!(f, "'\\n'"),
+                '\t' => write!(f, "'\\t'"),
+                '\r' => write!(f, "'\\r'"),
+                '\0' => write!(f, "'\\0'"),
+                '\\' | '\'' => write!(f, "'\\{}'", c),
+                _ => write!(f, "'{}'", c),
+            },
+            Literal::Nil => write!(f, "nil"),
         }
     }
 }
@@ -84,3 +252,46 @@ impl Display for ParseLiteralErr {
         write!(f, "Could not parse literal {}: {}", self.literal, self.message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_numbers_display_differently_from_integers() {
+        assert_eq!(Literal::Number(5.0).to_string(), "5.0");
+        assert_eq!(Literal::Integer(5).to_string(), "5");
+        assert_eq!(Literal::Number(-0.0).to_string(), "-0.0");
+        assert_eq!(Literal::Number(1e20).to_string(), "1e20");
+    }
+
+    #[test]
+    fn fractional_numbers_display_as_written() {
+        assert_eq!(Literal::Number(2.5).to_string(), "2.5");
+        assert_eq!(Literal::Number(0.1).to_string(), "0.1");
+        assert_eq!(Literal::Number(1e-7).to_string(), "1e-7");
+    }
+
+    #[test]
+    fn chars_display_as_they_are_written() {
+        assert_eq!(Literal::Char('a').to_string(), "'a'");
+        assert_eq!(Literal::Char('\n').to_string(), "'\\n'");
+        assert_eq!(Literal::Char('\'').to_string(), "'\\''");
+        assert_eq!(Literal::Char('"').to_string(), "'\"'");
+    }
+
+    #[test]
+    fn chars_round_trip() {
+        for c in &['a', 'é', ' ', '"', '\n', '\t', '\r', '\0', '\\', '\''] {
+            let displayed = Literal::Char(*c).to_string();
+            assert_eq!(displayed.parse::<Literal>().unwrap(), Literal::Char(*c), "parsing {}", displayed);
+        }
+    }
+
+    #[test]
+    fn displayed_numbers_parse_back_to_themselves() {
+        for n in &[5.0, 2.5, 1e20, 1e-7] {
+            assert_eq!(Literal::number(&Literal::Number(*n).to_string()).unwrap(), Literal::Number(*n));
+        }
+    }
+}
@@ -1,25 +1,34 @@
-mod token_type;
-mod literal;
-mod token;
-mod scanner;
+extern crate rox;
 
 use std::env;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
 
+use rox::scanner::{ScanContext, ScannerBuilder};
+
 fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 2 {
-        println!("Usage: rox [script]");
-        std::process::exit(64);
+    let mut args = env::args().skip(1);
+    let mut builder = ScannerBuilder::new();
+    let mut script = None;
+
+    while let Some(arg) = args.next() {
+        if arg == "--max-errors" {
+            match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => builder = builder.max_errors(n),
+                None => usage(),
+            }
+        } else if script.is_none() {
+            script = Some(arg);
+        } else {
+            usage();
+        }
     }
 
-    let result = if args.len() == 2 {
-        let file_name = &args[1];
-        run_file(file_name)
-    } else {
-        run_prompt()
+    let context = builder.build();
+    let result = match script {
+        Some(file_name) => run_file(&context, &file_name),
+        None => run_prompt(&context),
     };
 
     match result {
@@ -28,66 +37,88 @@ fn main() -> io::Result<()> {
     }
 }
 
+fn usage() -> ! {
+    println!("Usage: rox [--max-errors N] [script]");
+    std::process::exit(64);
+}
+
 type RoxResult = Result<(), RoxError>;
 
-fn run_file(path: &String) -> RoxResult {
+fn run_file(context: &ScanContext, path: &str) -> RoxResult {
     // since this program just makes a single, large read of the file
     // it doesn't make sense to bother with a BufReader.
     // maybe this will change in the future.
     let mut file = File::open(path).unwrap();
     let mut contents = String::new();
     file.read_to_string(&mut contents).unwrap();
-    run(contents)
+    run(context, &contents)
 }
 
-fn run_prompt() -> RoxResult {
+fn run_prompt(context: &ScanContext) -> RoxResult {
     let stdin = io::stdin();
 
     loop {
         print!("> ");
-        io::stdout().flush();
+        let _ = io::stdout().flush();
 
         let mut line = String::new();
 
         line.truncate(0); // read_line appends so we should clear the buffer
         match stdin.read_line(&mut line) {
             Err(_) => println!("Sorry, i didn't catch that!"),
-            Ok(_) => run(line).unwrap_or_else(|e| { e.report(); }),
+            // nothing read means stdin is closed, so there's no more to come
+            Ok(0) => {
+                println!();
+                return Ok(())
+            },
+            Ok(_) => run(context, &line).unwrap_or_else(|e| { let _ = e.report(); }),
         }
     }
 }
 
-fn run(source: String) -> Result<(), RoxError> {
-    match scanner::scan(source) {
-        Err(e) => Err(RoxError::new(Box::new(e))),
-        Ok(tokens) => {
-            for token in tokens.iter() {
-                println!("{}", token)
-            }
+fn run(context: &ScanContext, source: &str) -> Result<(), RoxError> {
+    let result = context.scan_indexed(source);
+    for warning in result.warnings.iter() {
+        eprintln!("{}", warning);
+    }
 
-            Ok(())
-        }
+    if !result.errors.is_empty() {
+        let errors = result.errors.into_iter()
+            .map(|e| Box::new(e) as Box<dyn std::error::Error>)
+            .collect();
+        return Err(RoxError::new(errors))
+    }
+
+    for token in result.tokens.iter() {
+        println!("{}", token)
     }
+
+    Ok(())
 }
 
 struct RoxError {
-    error: Box<std::error::Error>,
+    errors: Vec<Box<dyn std::error::Error>>,
 }
 
 impl RoxError {
-    fn new(error: Box<std::error::Error>) -> Self {
-        RoxError { error: error }
+    fn new(errors: Vec<Box<dyn std::error::Error>>) -> Self {
+        RoxError { errors }
     }
 
 
     fn report(&self) -> std::io::Result<()> {
-        let message = if let Some(cause) = self.error.cause() {
-            format!("Error: {}\n\t{}", self.error, cause)
-        } else {
-            format!("Error: {}\n", self.error)
-        };
+        let mut stderr = io::stderr();
+        for error in self.errors.iter() {
+            let message = if let Some(cause) = error.source() {
+                format!("Error: {}\n\t{}", error, cause)
+            } else {
+                format!("Error: {}\n", error)
+            };
+
+            stderr.write_all(message.as_bytes())?;
+        }
 
-        io::stderr().write_all(message.as_bytes())
+        Ok(())
     }
 
 }
@@ -0,0 +1,125 @@
+use std::cmp;
+
+use scanner::scan_indexed;
+use token_type::Type as TT;
+
+#[derive(Debug,Default)]
+pub struct DocumentMetrics {
+    // in chars, not bytes
+    pub longest_line: usize,
+    pub longest_token: Option<String>,
+    pub keywords: usize,
+    pub identifiers: usize,
+    pub numbers: usize,
+    pub strings: usize,
+    // how deep (), {} nest
+    pub max_depth: usize,
+}
+
+// a document being edited is usually broken somewhere, so this goes on
+// past anything that doesn't scan and counts whatever does.
+pub fn metrics(source: &str) -> DocumentMetrics {
+    let scanned = scan_indexed(source);
+    let mut metrics = DocumentMetrics::default();
+    let mut depth: usize = 0;
+    // where each interpolated string we're inside of starts
+    let mut interpolations = Vec::new();
+
+    // comments and blank space never become tokens, so lines can't be
+    // measured from them and have to be read off the source instead.
+    // the scan already found where each one starts, so that only goes over
+    // every character once more, and lines end wherever the scanner
+    // thinks they do, `\r` and all
+    for line in 1..=scanned.line_offsets.len() {
+        let length = scanned.line(source, line as u64).map(|l| l.chars().count()).unwrap_or(0);
+        metrics.longest_line = cmp::max(metrics.longest_line, length);
+    }
+
+    for token in scanned.tokens {
+        match token.token_type {
+            TT::LeftParen | TT::LeftBrace => {
+                depth += 1;
+                metrics.max_depth = cmp::max(metrics.max_depth, depth);
+            },
+            TT::RightParen | TT::RightBrace => depth = depth.saturating_sub(1),
+            TT::Identifier => metrics.identifiers += 1,
+            TT::Number => metrics.numbers += 1,
+            TT::String => metrics.strings += 1,
+            // an interpolated string is one string, however many pieces it comes in
+            TT::InterpStart => {
+                metrics.strings += 1;
+                interpolations.push(token.offsets.0);
+            },
+            tt if tt.is_keyword() => metrics.keywords += 1,
+            _ => (),
+        }
+
+        // the pieces of an interpolated string aren't tokens in their own right.
+        // once it's over, the whole thing is up for being the longest
+        let lexeme = match token.token_type {
+            TT::InterpStart | TT::InterpPart => continue,
+            TT::InterpEnd => match interpolations.pop() {
+                Some(start) => &source[start..token.offsets.1],
+                None => continue,
+            },
+            _ => &*token.lexeme,
+        };
+
+        let longer = metrics.longest_token.as_ref()
+            .map(|longest| lexeme.chars().count() > longest.chars().count())
+            .unwrap_or(true);
+        if longer {
+            metrics.longest_token = Some(lexeme.to_string());
+        }
+    }
+
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_for_a_small_snippet() {
+        let source = "var greeting = \"hello\";\rfun f(a) { if (a) { print (1 + 2); } }\r\n// é\n";
+        let metrics = metrics(source);
+
+        assert_eq!(metrics.longest_line, 38);
+        assert_eq!(metrics.longest_token.as_deref(), Some("greeting"));
+        assert_eq!(metrics.keywords, 4);
+        assert_eq!(metrics.identifiers, 4);
+        assert_eq!(metrics.numbers, 2);
+        assert_eq!(metrics.strings, 1);
+        assert_eq!(metrics.max_depth, 3);
+    }
+
+    #[test]
+    fn metrics_carry_on_past_errors() {
+        let metrics = metrics("var a = @;\nvar b = #;\n");
+
+        assert_eq!(metrics.keywords, 2);
+        assert_eq!(metrics.identifiers, 2);
+        assert_eq!(metrics.longest_line, 10);
+    }
+
+    #[test]
+    fn interpolated_strings_count_once() {
+        let metrics = metrics("\"a ${b} c\" \"d\"");
+
+        assert_eq!(metrics.strings, 2);
+        assert_eq!(metrics.identifiers, 1);
+        assert_eq!(metrics.longest_token.as_deref(), Some("\"a ${b} c\""));
+
+        let nested = super::metrics("\"x${\"y${z}\"}w\"");
+        assert_eq!(nested.strings, 2);
+        assert_eq!(nested.longest_token.as_deref(), Some("\"x${\"y${z}\"}w\""));
+    }
+
+    #[test]
+    fn a_lone_carriage_return_ends_a_line() {
+        let metrics = metrics("a\rbb\rccc");
+
+        assert_eq!(metrics.longest_line, 3);
+    }
+}
@@ -0,0 +1,720 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::error::Error;
+
+use ast::{binary_operator, operand_bindings, Expr, Stmt};
+use token::Token;
+use tokens::significant;
+use token_type::Type as TT;
+use literal::Literal as Lit;
+
+type Parse<T> = Result<T, ParseError>;
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    insert_semicolons: bool,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let mut tokens: Vec<Token> = significant(&tokens).cloned().collect();
+
+        // the rules lean on there always being an eof to stop at
+        if tokens.last().map(|t| t.token_type) != Some(TT::Eof) {
+            let end = tokens.last().map(|t| t.end).unwrap_or((1, 0));
+            tokens.push(Token::synthetic(TT::Eof, String::new(), end));
+        }
+
+        Parser { tokens, current: 0, insert_semicolons: false }
+    }
+
+    // lets a line break end a statement the way a `;` would, like javascript.
+    // off by default, so semicolons are required.
+    pub fn insert_semicolons(mut self, insert: bool) -> Self {
+        self.insert_semicolons = insert;
+        self
+    }
+
+    // every statement in the source. a syntax error
+    // doesn't stop the rest from being read: it's recorded, the parser
+    // skips ahead to the next statement, and carries on from there.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(e) => {
+                    errors.push(e);
+                    self.synchronize();
+                },
+            }
+        }
+
+        if errors.is_empty() { Ok(statements) } else { Err(errors) }
+    }
+
+    pub fn parse_expression(&mut self) -> Parse<Expr> {
+        self.expression()
+    }
+
+    // declarations are statements that bind names
+    fn declaration(&mut self) -> Parse<Stmt> {
+        if self.advance_if(&[TT::Var]) {
+            return self.var_declaration()
+        }
+
+        self.statement()
+    }
+
+    fn var_declaration(&mut self) -> Parse<Stmt> {
+        let name = self.consume(TT::Identifier, "Expected variable name")?;
+
+        let initializer = if self.advance_if(&[TT::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.end_statement("Expected ';' after variable declaration")?;
+        Ok(Stmt::Var { name, initializer })
+    }
+
+    fn statement(&mut self) -> Parse<Stmt> {
+        if self.advance_if(&[TT::Print]) {
+            return self.print_statement()
+        }
+
+        self.expression_statement()
+    }
+
+    fn print_statement(&mut self) -> Parse<Stmt> {
+        let value = self.expression()?;
+        self.end_statement("Expected ';' after value")?;
+        Ok(Stmt::Print(value))
+    }
+
+    fn expression_statement(&mut self) -> Parse<Stmt> {
+        let expr = self.expression()?;
+        self.end_statement("Expected ';' after expression")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn expression(&mut self) -> Parse<Expr> {
+        self.binary(0)
+    }
+
+    // precedence climbing over the operator table in ast.
+    // takes operators that bind at least `min` tightly, and leaves
+    // looser ones for a caller further up: in `1 * 2 + 3` the `*` call
+    // stops at `+`, and `a ?? b == c` falls back to the whole comparison.
+    // how far the right side reaches decides the grouping,
+    // so `a - b - c` folds into `(- (- a b) c)`.
+    fn binary(&mut self, min: u8) -> Parse<Expr> {
+        let mut expr = self.unary()?;
+
+        while let Some((power, _)) = binary_operator(self.peek().token_type) {
+            if power < min {
+                break
+            }
+
+            let operator = self.advance().clone();
+            if let Some(error) = self.chained_comparison(&expr, &operator) {
+                return Err(error)
+            }
+
+            let (_, right_needs) = operand_bindings(operator.token_type);
+            let right = self.binary(right_needs)?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    // `1 < x < 3` would compare `1 < x`, which is a boolean, with 3.
+    // that's never what it means, so it's better caught here than as
+    // a confusing type error later. `(1 < x) < 3` says it's intended.
+    fn chained_comparison(&self, left: &Expr, operator: &Token) -> Option<ParseError> {
+        let first = match *left {
+            Expr::Binary { operator: ref first, .. } => first,
+            _ => return None,
+        };
+
+        if TT::COMPARISONS.contains(&first.token_type) && TT::COMPARISONS.contains(&operator.token_type) {
+            let message = format!("chained comparison `a {0} b {1} c` is not supported; use `a {0} b and b {1} c`",
+                                  first.lexeme, operator.lexeme);
+            Some(self.error(operator, &message))
+        } else {
+            None
+        }
+    }
+
+    // prefix operators nest, so `!!x` is `(! (! x))`,
+    // and bind tighter than anything binary: `-2 * 3` is `(* (- 2) 3)`
+    fn unary(&mut self) -> Parse<Expr> {
+        if self.peek().token_type.is_unary_operator() {
+            let operator = self.advance().clone();
+            let right = self.unary()?;
+            if operator.token_type == TT::Plus && !could_be_number(&right) {
+                return Err(self.error(&operator, "Unary '+' only applies to numbers"))
+            }
+            return Ok(Expr::Unary { operator, right: Box::new(right) })
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Parse<Expr> {
+        if self.advance_if(&[TT::LeftParen]) {
+            let expr = self.expression()?;
+            self.consume(TT::RightParen, "Expected ')' after expression")?;
+            return Ok(Expr::Grouping(Box::new(expr)))
+        }
+
+        let token = self.consume_any(TT::LITERALS, "Expected expression")?;
+        let value = match token.token_type {
+            TT::False => Lit::Boolean(false),
+            TT::True => Lit::Boolean(true),
+            TT::Nil => Lit::Nil,
+            _ => match token.literal {
+                Some(ref literal) => literal.clone(),
+                None => return Err(self.error(&token, "Expected a value")),
+            },
+        };
+
+        Ok(Expr::Literal { value, token })
+    }
+
+    // throws away tokens until just past a `;` or right before
+    // something that starts a statement, where parsing can pick back up
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == TT::Semicolon {
+                return
+            }
+
+            if TT::STATEMENT_STARTS.contains(&self.peek().token_type) {
+                return
+            }
+
+            self.advance();
+        }
+    }
+
+    fn advance_if(&mut self, types: &[TT]) -> bool {
+        if self.check_any(types) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    // the next token has to be `tt`; anything else is reported where it stands
+    fn consume(&mut self, tt: TT, message: &str) -> Parse<Token> {
+        self.consume_any(&[tt], message)
+    }
+
+    // like consume, for when any one of `types` will do
+    fn consume_any(&mut self, types: &[TT], message: &str) -> Parse<Token> {
+        if self.check_any(types) {
+            Ok(self.advance().clone())
+        } else {
+            Err(self.error(self.peek(), message))
+        }
+    }
+
+    // the `;` after a statement. when semicolons are being inserted,
+    // one goes wherever the statement is followed by a line break,
+    // the end of a block or the end of the source.
+    // the rules only come looking for a `;` once the statement has taken
+    // every token it could, so whatever is next can't carry it on.
+    fn end_statement(&mut self, message: &str) -> Parse<Token> {
+        if self.insert_semicolons && !self.check(TT::Semicolon) && self.at_line_break() {
+            return Ok(Token::synthetic(TT::Semicolon, ";", self.previous().end))
+        }
+
+        self.consume(TT::Semicolon, message)
+    }
+
+    fn at_line_break(&self) -> bool {
+        self.is_at_end()
+            || self.check(TT::RightBrace)
+            || self.peek().starts_line(Some(self.previous()))
+    }
+
+    fn check(&self, tt: TT) -> bool {
+        self.peek().token_type == tt
+    }
+
+    fn check_any(&self, types: &[TT]) -> bool {
+        types.contains(&self.peek().token_type)
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.check(TT::Eof)
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current.saturating_sub(1)]
+    }
+
+    fn error(&self, token: &Token, message: &str) -> ParseError {
+        ParseError { token: Box::new(token.clone()), message: message.to_string() }
+    }
+}
+
+// unary `+` leaves a number as it is and means nothing for anything else.
+// only literals can be told apart before running, so `+x` has to wait.
+fn could_be_number(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Literal { value: Lit::Number(_), .. } | Expr::Literal { value: Lit::Integer(_), .. } => true,
+        Expr::Literal { .. } => false,
+        Expr::Grouping(ref inner) => could_be_number(inner),
+        // `!` always makes a boolean, and a sign in front changes nothing
+        Expr::Unary { ref operator, .. } if operator.token_type == TT::Bang => false,
+        Expr::Unary { ref right, .. } => could_be_number(right),
+        Expr::Binary { .. } => true,
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    // boxed so results stay small on the happy path
+    token: Box<Token>,
+    message: String,
+}
+
+impl ParseError {
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let (line, column) = self.token.position;
+        if self.token.token_type == TT::Eof {
+            write!(f, "Error parsing code at end, line {}, column {}: {}",
+                   line, column, self.message)
+        } else {
+            write!(f, "Error parsing code at '{}', line {}, column {}: {}",
+                   self.token.lexeme, line, column, self.message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstPrinter;
+    use scanner::scan;
+
+    fn parser(source: &str) -> Parser {
+        Parser::new(scan(source).unwrap())
+    }
+
+    fn expression(source: &str) -> String {
+        AstPrinter.print(&parser(source).parse_expression().unwrap())
+    }
+
+    fn value(expr: &Expr) -> Option<&Lit> {
+        match *expr {
+            Expr::Literal { ref value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn consume_any_takes_a_token_from_the_set() {
+        let mut parser = parser("1 +");
+        let token = parser.consume_any(&[TT::String, TT::Number], "Expected a number or string").unwrap();
+
+        assert_eq!(token.token_type, TT::Number);
+        assert_eq!(parser.peek().token_type, TT::Plus);
+    }
+
+    #[test]
+    fn consume_any_reports_a_token_outside_the_set() {
+        let mut parser = parser("+ 1");
+        let error = parser.consume_any(&[TT::String, TT::Number], "Expected a number or string").unwrap_err();
+
+        assert_eq!(error.token().token_type, TT::Plus);
+        assert_eq!(error.message(), "Expected a number or string");
+        // nothing is used up by a failed consume
+        assert_eq!(parser.peek().token_type, TT::Plus);
+    }
+
+    #[test]
+    fn nil_coalescing_binds_loosest() {
+        assert_eq!(expression("nil ?? 1"), "(?? nil 1)");
+        assert_eq!(expression("nil ?? 1 == 2"), "(?? nil (== 1 2))");
+        assert_eq!(expression("nil ?? nil ?? 3"), "(?? (?? nil nil) 3)");
+    }
+
+    #[test]
+    fn nil_coalescing_makes_a_statement() {
+        let statements = parser("1 ?? 2;").parse().unwrap();
+
+        assert_eq!(statements.len(), 1);
+    }
+
+    #[test]
+    fn whole_numbers_print_with_their_fraction() {
+        assert_eq!(expression("2.0 + 2"), "(+ 2.0 2)");
+    }
+
+    #[test]
+    fn products_bind_tighter_than_sums() {
+        let expr = parser("1 + 2 * 3").parse_expression().unwrap();
+
+        match expr {
+            Expr::Binary { left, operator, right } => {
+                assert_eq!(operator.token_type, TT::Plus);
+                assert_eq!(value(&left), Some(&Lit::Integer(1)));
+                match *right {
+                    Expr::Binary { left, operator, right } => {
+                        assert_eq!(operator.token_type, TT::Star);
+                        assert_eq!(value(&left), Some(&Lit::Integer(2)));
+                        assert_eq!(value(&right), Some(&Lit::Integer(3)));
+                    },
+                    other => panic!("expected 2 * 3, got {:?}", other),
+                }
+            },
+            other => panic!("expected a sum, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_bad_expression_reports_where_it_went_wrong() {
+        let error = parser("1 + ;").parse_expression().unwrap_err();
+
+        assert_eq!(error.token().token_type, TT::Semicolon);
+        assert_eq!(error.message(), "Expected expression");
+        assert_eq!(error.to_string(), "Error parsing code at ';', line 1, column 5: Expected expression");
+    }
+
+    #[test]
+    fn parentheses_make_groups() {
+        assert_eq!(expression("(1 + 2)"), "(group (+ 1 2))");
+        assert_eq!(expression("((1))"), "(group (group 1))");
+        assert_eq!(expression("(1 + 2) * 3"), "(* (group (+ 1 2)) 3)");
+    }
+
+    #[test]
+    fn an_unclosed_group_reports_what_came_instead() {
+        let error = parser("(1 + 2").parse_expression().unwrap_err();
+
+        assert_eq!(error.token().token_type, TT::Eof);
+        assert_eq!(error.message(), "Expected ')' after expression");
+        assert_eq!(error.to_string(), "Error parsing code at end, line 1, column 7: Expected ')' after expression");
+
+        let error = parser("(1 + 2;").parse_expression().unwrap_err();
+        assert_eq!(error.token().token_type, TT::Semicolon);
+    }
+
+    #[test]
+    fn unary_operators_nest_and_bind_tighter_than_factors() {
+        assert_eq!(expression("-2 * 3"), "(* (- 2) 3)");
+        assert_eq!(expression("!!true"), "(! (! true))");
+        assert_eq!(expression("--5"), "(- (- 5))");
+        assert_eq!(expression("-(2 * 3)"), "(- (group (* 2 3)))");
+    }
+
+    #[test]
+    fn binary_operators_are_left_associative() {
+        assert_eq!(expression("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(expression("8 / 4 / 2"), "(/ (/ 8 4) 2)");
+        assert_eq!(expression("1 % 2 % 3"), "(% (% 1 2) 3)");
+        assert_eq!(expression("1 == 2 != 3"), "(!= (== 1 2) 3)");
+    }
+
+    #[test]
+    fn binary_operators_follow_precedence() {
+        assert_eq!(expression("1 + 2 == 3 * 4 - 5"), "(== (+ 1 2) (- (* 3 4) 5))");
+        assert_eq!(expression("1 < 2 == 3 >= 4"), "(== (< 1 2) (>= 3 4))");
+        assert_eq!(expression("1 + 2 * 3 % 4"), "(+ 1 (% (* 2 3) 4))");
+    }
+
+    #[test]
+    fn parsing_carries_on_past_errors() {
+        let errors = parser("print (1;\nprint 2;\nvar = 3;\nprint 4 +;\nprint 5;").parse().unwrap_err();
+        let found: Vec<(u64, &str)> = errors.iter()
+            .map(|e| (e.token().position.0, e.message()))
+            .collect();
+
+        assert_eq!(found, vec![
+            (1, "Expected ')' after expression"),
+            (3, "Expected variable name"),
+            (4, "Expected expression"),
+        ]);
+    }
+
+    #[test]
+    fn synchronizing_stops_at_the_next_statement() {
+        let mut parser = parser("1 2 3 print 4;");
+        assert!(parser.parse_expression().is_ok());
+        parser.synchronize();
+
+        assert_eq!(parser.peek().token_type, TT::Print);
+    }
+
+    fn statements(source: &str) -> Vec<Stmt> {
+        parser(source).parse().unwrap()
+    }
+
+    #[test]
+    fn parses_statements_until_the_end() {
+        let statements = statements("1 + 2;\nprint 3;\n\"four\";");
+
+        assert_eq!(statements.len(), 3);
+        assert!(matches!(statements[0], Stmt::Expression(Expr::Binary { .. })));
+        assert!(matches!(statements[1], Stmt::Print(ref e) if value(e) == Some(&Lit::Integer(3))));
+        assert!(matches!(statements[2], Stmt::Expression(ref e) if value(e) == Some(&Lit::String("four".to_owned()))));
+    }
+
+    #[test]
+    fn nothing_to_parse_is_no_statements() {
+        assert!(statements("").is_empty());
+        assert!(statements("// just a comment\n").is_empty());
+    }
+
+    #[test]
+    fn a_statement_needs_its_semicolon() {
+        let errors = parser("1 + 2").parse().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].token().token_type, TT::Eof);
+        assert_eq!(errors[0].message(), "Expected ';' after expression");
+    }
+
+    #[test]
+    fn parses_a_print_statement() {
+        let statements = statements("print 1 + 2;");
+
+        match statements[..] {
+            [Stmt::Print(ref value)] => assert_eq!(AstPrinter.print(value), "(+ 1 2)"),
+            ref other => panic!("expected one print, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_print_needs_its_semicolon() {
+        let errors = parser("print 1 + 2\nprint 3;").parse().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        // pointing at what came instead of the `;`
+        assert_eq!(errors[0].token().token_type, TT::Print);
+        assert_eq!(errors[0].token().position, (2, 1));
+        assert_eq!(errors[0].message(), "Expected ';' after value");
+    }
+
+    #[test]
+    fn parses_variable_declarations() {
+        let statements = statements("var x = 1 + 2;\nvar y;");
+
+        match statements[..] {
+            [Stmt::Var { name: ref x, initializer: Some(ref value) },
+             Stmt::Var { name: ref y, initializer: None }] => {
+                assert_eq!(&*x.lexeme, "x");
+                assert_eq!(AstPrinter.print(value), "(+ 1 2)");
+                assert_eq!(&*y.lexeme, "y");
+            },
+            ref other => panic!("expected two declarations, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_declaration_needs_a_name() {
+        let errors = parser("var = 1;\nvar 2;").parse().unwrap_err();
+        let found: Vec<(TT, &str)> = errors.iter()
+            .map(|e| (e.token().token_type, e.message()))
+            .collect();
+
+        assert_eq!(found, vec![
+            (TT::Equal, "Expected variable name"),
+            (TT::Number, "Expected variable name"),
+        ]);
+    }
+
+    #[test]
+    fn a_declaration_needs_its_semicolon() {
+        let errors = parser("var x = 1").parse().unwrap_err();
+
+        assert_eq!(errors[0].message(), "Expected ';' after variable declaration");
+    }
+
+    fn with_semicolons_inserted(source: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        parser(source).insert_semicolons(true).parse()
+    }
+
+    // what each statement says, leaving out where it was
+    fn printed(statements: &[Stmt]) -> Vec<String> {
+        statements.iter().map(|statement| match *statement {
+            Stmt::Expression(ref expr) => AstPrinter.print(expr),
+            Stmt::Print(ref value) => format!("(print {})", AstPrinter.print(value)),
+            Stmt::Var { ref name, ref initializer } => match *initializer {
+                Some(ref value) => format!("(var {} {})", name.lexeme, AstPrinter.print(value)),
+                None => format!("(var {})", name.lexeme),
+            },
+        }).collect()
+    }
+
+    #[test]
+    fn line_breaks_stand_in_for_semicolons() {
+        let explicit = printed(&statements("var x = 1;\nprint 2 + 3;\n\"done\";"));
+        let inserted = with_semicolons_inserted("var x = 1\nprint 2 + 3\n\"done\"").unwrap();
+
+        assert_eq!(printed(&inserted), explicit);
+        assert_eq!(explicit, vec!["(var x 1)", "(print (+ 2 3))", "done"]);
+        // written ones are still fine
+        assert_eq!(printed(&with_semicolons_inserted("var x = 1;\nprint 2 + 3;\n\"done\";").unwrap()), explicit);
+    }
+
+    #[test]
+    fn statements_carry_on_over_a_line_break_when_they_can() {
+        let statements = with_semicolons_inserted("print 1\n  + 2\nprint 3").unwrap();
+
+        assert_eq!(statements.len(), 2);
+        match statements[0] {
+            Stmt::Print(ref value) => assert_eq!(AstPrinter.print(value), "(+ 1 2)"),
+            ref other => panic!("expected a print, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn statements_on_one_line_still_need_semicolons() {
+        let errors = with_semicolons_inserted("print 1 print 2").unwrap_err();
+
+        assert_eq!(errors[0].message(), "Expected ';' after value");
+        assert_eq!(errors[0].token().token_type, TT::Print);
+    }
+
+    #[test]
+    fn the_operator_table_reads_like_the_grammar_did() {
+        // what the function-per-level parser made of these
+        let suite = vec![
+            ("1 + 2 * 3 - 4", "(- (+ 1 (* 2 3)) 4)"),
+            ("1 * 2 + 3 * 4", "(+ (* 1 2) (* 3 4))"),
+            ("-1 - -2", "(- (- 1) (- 2))"),
+            ("1 < 2 == 3 > 4", "(== (< 1 2) (> 3 4))"),
+            ("1 ?? 2 + 3 * 4 == 5", "(?? 1 (== (+ 2 (* 3 4)) 5))"),
+            ("!(1 + 2) * 3 / 4 % 5", "(% (/ (* (! (group (+ 1 2))) 3) 4) 5)"),
+            ("1 - 2 + 3 - 4", "(- (+ (- 1 2) 3) 4)"),
+            ("1 == 2 == 3", "(== (== 1 2) 3)"),
+            ("1 ?? 2 ?? 3 == 4", "(?? (?? 1 2) (== 3 4))"),
+            ("2 * (3 + 4) - -5 >= 6 != !true", "(!= (>= (- (* 2 (group (+ 3 4))) (- 5)) 6) (! true))"),
+            ("1 / 2 * 3 % 4 - 5", "(- (% (* (/ 1 2) 3) 4) 5)"),
+        ];
+
+        for (source, tree) in suite {
+            assert_eq!(expression(source), tree, "parsing {}", source);
+        }
+    }
+
+    #[test]
+    fn every_binary_operator_is_in_the_table() {
+        let operators = [TT::QuestionQuestion, TT::BangEqual, TT::EqualEqual, TT::Greater, TT::GreaterEqual,
+                         TT::Less, TT::LessEqual, TT::Minus, TT::Plus, TT::Slash, TT::Star, TT::Percent];
+
+        for operator in &operators {
+            assert!(operator.is_binary_operator());
+            assert!(binary_operator(*operator).is_some(), "{:?} has no precedence", operator);
+        }
+        assert_eq!(binary_operator(TT::Bang), None);
+        assert_eq!(binary_operator(TT::And), None);
+    }
+
+    #[test]
+    fn chained_comparisons_are_an_error() {
+        let error = parser("1 < 2 < 3").parse_expression().unwrap_err();
+
+        assert_eq!(error.token().position, (1, 7));
+        assert_eq!(error.message(), "chained comparison `a < b < c` is not supported; use `a < b and b < c`");
+
+        let error = parser("1 <= 2 + 3 > 4").parse_expression().unwrap_err();
+        assert_eq!(error.message(), "chained comparison `a <= b > c` is not supported; use `a <= b and b > c`");
+    }
+
+    #[test]
+    fn comparisons_that_only_look_chained_are_fine() {
+        assert_eq!(expression("(1 < 2) < 3"), "(< (group (< 1 2)) 3)");
+        assert_eq!(expression("1 < 2 == 3 < 4"), "(== (< 1 2) (< 3 4))");
+        assert_eq!(expression("1 + 2 < 3"), "(< (+ 1 2) 3)");
+    }
+
+    #[test]
+    fn unary_plus_goes_in_front_of_numbers() {
+        assert_eq!(expression("+5"), "(+ 5)");
+        assert_eq!(expression("+2.5 * 2"), "(* (+ 2.5) 2)");
+        assert_eq!(expression("1 + +(2)"), "(+ 1 (+ (group 2)))");
+        assert_eq!(expression("+-5"), "(+ (- 5))");
+    }
+
+    #[test]
+    fn unary_plus_on_anything_else_is_an_error() {
+        for source in &["+\"x\"", "+true", "+nil", "+(\"x\")", "+!true", "+-\"x\"", "+(-(!1))", "++nil"] {
+            let error = parser(source).parse_expression().unwrap_err();
+
+            assert_eq!(error.token().token_type, TT::Plus, "parsing {}", source);
+            assert_eq!(error.message(), "Unary '+' only applies to numbers");
+        }
+    }
+
+    #[test]
+    fn literals_know_where_they_were_written() {
+        let expr = parser("1 +\n  \"two\"").parse_expression().unwrap();
+
+        match expr {
+            Expr::Binary { left, right, .. } => match (*left, *right) {
+                (Expr::Literal { token: one, .. }, Expr::Literal { token: two, value }) => {
+                    assert_eq!(one.span(), ((1, 1), (1, 2)));
+                    assert_eq!(two.span(), ((2, 3), (2, 8)));
+                    assert_eq!(value, Lit::String("two".to_owned()));
+                },
+                other => panic!("expected two literals, got {:?}", other),
+            },
+            other => panic!("expected a sum, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keyword_literals_keep_their_token_too() {
+        let expr = parser("  nil").parse_expression().unwrap();
+
+        match expr {
+            Expr::Literal { value: Lit::Nil, ref token } => assert_eq!(token.position, (1, 3)),
+            other => panic!("expected nil, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn no_semicolon_goes_after_a_string_that_only_spans_lines() {
+        let errors = with_semicolons_inserted("print \"a\nb\" print 3").unwrap_err();
+
+        assert_eq!(errors[0].message(), "Expected ';' after value");
+        assert_eq!(errors[0].token().position, (2, 4));
+
+        assert_eq!(with_semicolons_inserted("print \"a\nb\"\nprint 3").unwrap().len(), 2);
+    }
+}
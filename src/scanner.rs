@@ -1,55 +1,772 @@
 use std::iter;
-use std::str::{FromStr,Chars};
+use std::mem;
+use std::io::{self, Read};
+use std::str::{self,FromStr,Chars};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::error::Error;
-use std::collections::{HashMap};
+use std::collections::{HashMap,HashSet};
+use std::sync::{Arc,Mutex,OnceLock};
+
+use unicode_xid::UnicodeXID;
 
 use token::Token;
 use token_type::Type as TT;
 use literal::Literal as Lit;
 
-pub fn scan(source: String) -> Result<Tokens, ScanError> {
-    Scanner::new(source.chars().peekable()).collect()
+pub fn scan(source: &str) -> Result<Tokens, ScanError> {
+    default_context().scan(source)
+}
+
+// scans only as far as the caller takes tokens
+pub fn tokens(source: &str) -> TokenStream<'_> {
+    default_context().tokens(source)
+}
+
+fn default_context() -> &'static ScanContext {
+    static CONTEXT: OnceLock<ScanContext> = OnceLock::new();
+    CONTEXT.get_or_init(ScanContext::new)
+}
+
+pub fn scan_reader<R: Read>(reader: R) -> Result<Tokens, ScanError> {
+    default_context().scan_reader(reader)
+}
+
+pub fn scan_first_statement(source: &str) -> Result<(Tokens, usize), ScanError> {
+    default_context().scan_first_statement(source)
+}
+
+// past this many errors a source is probably not worth reading further,
+// and editors don't want to be flooded with diagnostics anyway.
+// a cap of 0 means keep going no matter what.
+pub const DEFAULT_MAX_ERRORS: usize = 100;
+
+pub fn scan_all(source: &str) -> (Tokens, Vec<ScanError>) {
+    default_context().scan_all(source)
+}
+
+pub fn scan_indexed(source: &str) -> ScanResult {
+    default_context().scan_indexed(source)
+}
+
+pub fn scan_all_capped(source: &str, max_errors: usize) -> (Tokens, Vec<ScanError>) {
+    ScannerBuilder::new()
+        .max_errors(max_errors)
+        .build()
+        .scan_all(source)
+}
+
+#[derive(Debug,Clone)]
+struct Options {
+    max_errors: usize,
+    strict_identifiers: bool,
+    number_suffixes: Vec<String>,
+    template_delimiters: (String, String),
+    keep_comments: bool,
+    keep_whitespace: bool,
+    tab_width: Column,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_errors: DEFAULT_MAX_ERRORS,
+            strict_identifiers: false,
+            number_suffixes: Vec::new(),
+            template_delimiters: ("{{".to_owned(), "}}".to_owned()),
+            keep_comments: false,
+            keep_whitespace: false,
+            tab_width: 1,
+        }
+    }
+}
+
+#[derive(Debug,Default)]
+pub struct ScannerBuilder {
+    options: Options,
+    reserved_words: Option<HashMap<&'static str, TT>>,
+    intern_identifiers: bool,
+}
+
+impl ScannerBuilder {
+    pub fn new() -> Self {
+        ScannerBuilder::default()
+    }
+
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.options.max_errors = max_errors;
+        self
+    }
+
+    // by default `123abc` is a number followed by an identifier;
+    // strict mode calls it what it probably is, a typo.
+    pub fn strict_identifiers(mut self, strict: bool) -> Self {
+        self.options.strict_identifiers = strict;
+        self
+    }
+
+    // lets numbers carry a type tag like `10i` or `255u8`.
+    // with none configured, `10i` stays a number followed by an identifier.
+    pub fn number_suffixes(mut self, suffixes: &[&str]) -> Self {
+        self.options.number_suffixes = suffixes.iter().map(|&s| s.to_owned()).collect();
+        self
+    }
+
+    // what scan_template looks for around embedded code, `{{` and `}}` by default
+    pub fn template_delimiters(mut self, open: &str, close: &str) -> Self {
+        self.options.template_delimiters = (open.to_owned(), close.to_owned());
+        self
+    }
+
+    // hands comments back as Comment tokens instead of skipping them,
+    // for tools like formatters that have to put them back.
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.options.keep_comments = keep;
+        self
+    }
+
+    // hands back each run of whitespace as a Whitespace token, so that
+    // the tokens can be pasted back together into the original source.
+    pub fn keep_whitespace(mut self, keep: bool) -> Self {
+        self.options.keep_whitespace = keep;
+        self
+    }
+
+    // how many columns a tab moves over, to line up with the user's editor
+    pub fn tab_width(mut self, width: Column) -> Self {
+        self.options.tab_width = width;
+        self
+    }
+
+    // replaces the keyword table outright; start from `reserved_words()`
+    // to add to or remove from lox's own keywords. words lox doesn't have
+    // a type for, like a `match` for some dsl, can be added as Keyword.
+    pub fn reserved_words(mut self, words: HashMap<&'static str, TT>) -> Result<Self, InvalidKeyword> {
+        if let Some(&word) = words.keys().find(|w| !is_identifier(w)) {
+            return Err(InvalidKeyword { keyword: word.to_owned() })
+        }
+        self.reserved_words = Some(words);
+        Ok(self)
+    }
+
+    // gives every identifier with the same name the same lexeme, across
+    // every document the context scans. worth it for something like a server
+    // that sees the same names over and over; the names are kept for as long
+    // as the context is.
+    pub fn intern_identifiers(mut self, intern: bool) -> Self {
+        self.intern_identifiers = intern;
+        self
+    }
+
+    pub fn build(self) -> ScanContext {
+        ScanContext {
+            reserved_words: self.reserved_words,
+            interner: if self.intern_identifiers { Some(Interner::default()) } else { None },
+            options: self.options,
+        }
+    }
+}
+
+// holds on to everything a scan needs that doesn't depend on the source,
+// so that scanning lots of documents doesn't rebuild it every time.
+#[derive(Debug)]
+pub struct ScanContext {
+    // None shares lox's own keywords rather than keeping a copy
+    reserved_words: Option<HashMap<&'static str, TT>>,
+    interner: Option<Interner>,
+    options: Options,
+}
+
+impl Default for ScanContext {
+    fn default() -> Self {
+        ScannerBuilder::new().build()
+    }
+}
+
+impl ScanContext {
+    pub fn new() -> Self {
+        ScanContext::default()
+    }
+
+    pub fn scan(&self, source: &str) -> Result<Tokens, ScanError> {
+        let mut tokens = Vec::with_capacity(expected_tokens(source));
+        for scan in self.scanner(source) {
+            tokens.push(scan?);
+        }
+        Ok(tokens)
+    }
+
+    // scan_all, plus an index of where each line starts
+    // so errors can be shown in context later
+    pub fn scan_indexed(&self, source: &str) -> ScanResult {
+        let (tokens, errors, warnings) = self.scan_recovering(source);
+        let line_offsets = iter::once(0)
+            .chain(source.char_indices()
+                   .filter(|&(i, c)| c == '\n' || c == '\r' && !source[i + 1..].starts_with('\n'))
+                   .map(|(i, _)| i + 1))
+            .collect();
+
+        ScanResult { tokens, line_offsets, errors, warnings, tab_width: self.options.tab_width }
+    }
+
+    pub fn scan_all(&self, source: &str) -> (Tokens, Vec<ScanError>) {
+        let (tokens, errors, _) = self.scan_recovering(source);
+        (tokens, errors)
+    }
+
+    fn scan_recovering(&self, source: &str) -> (Tokens, Vec<ScanError>, Vec<ScanError>) {
+        let mut tokens = Vec::with_capacity(expected_tokens(source));
+        let mut errors = Vec::new();
+
+        // the scanner has already moved past whatever it choked on
+        // by the time it reports an error, so we can just keep pulling.
+        let mut scanner = self.scanner(source);
+        scanner.recovering = true;
+
+        for scan in scanner.by_ref() {
+            match scan {
+                Ok(token) => tokens.push(token),
+                // a source with exactly as many errors as allowed is read to
+                // the end; it's the one after that which means giving up.
+                Err(e) => {
+                    if self.options.max_errors > 0 && errors.len() >= self.options.max_errors {
+                        errors.push(ScanError {
+                            kind: ScanErrorKind::TooManyErrors,
+                            position: e.position,
+                            message: "too many errors; stopping".to_owned(),
+                        });
+                        break
+                    }
+                    errors.push(e);
+                },
+            }
+        }
+
+        (tokens, errors, scanner.warnings)
+    }
+
+    // the tokens up to and including the end of the first top-level statement,
+    // along with the byte offset it ended at. a repl can run whatever is
+    // complete and hold on to the rest; if nothing is complete yet the error
+    // is Incomplete and the caller should wait for more input.
+    // a string, comment or character still open when the input runs out is
+    // Incomplete as well, since that's how one looks halfway through typing it.
+    // a `)` or `}` with nothing to close can't be fixed by typing more,
+    // so that's an error straight away.
+    pub fn scan_first_statement(&self, source: &str) -> Result<(Tokens, usize), ScanError> {
+        let mut scanner = self.scanner(source);
+        let mut tokens = Vec::new();
+        let mut parens = 0;
+        let mut braces = 0;
+
+        loop {
+            let token = match scanner.next() {
+                None => break,
+                Some(Err(error)) => {
+                    let unterminated = matches!(error.kind,
+                                                 ScanErrorKind::UnterminatedString |
+                                                 ScanErrorKind::UnterminatedComment |
+                                                 ScanErrorKind::UnterminatedChar);
+                    if unterminated && scanner.source.peek().is_none() {
+                        return Err(ScanError {
+                            kind: ScanErrorKind::Incomplete,
+                            position: error.position,
+                            message: "needs more input".to_owned(),
+                        })
+                    }
+                    return Err(error)
+                },
+                Some(Ok(token)) => token,
+            };
+            let tt = token.token_type;
+
+            let complete = match tt {
+                TT::LeftParen => { parens += 1; false },
+                TT::RightParen => { parens -= 1; false },
+                TT::LeftBrace => { braces += 1; false },
+                TT::RightBrace => { braces -= 1; braces == 0 && parens == 0 },
+                TT::Semicolon => braces == 0 && parens == 0,
+                _ => false,
+            };
+            if parens < 0 || braces < 0 {
+                return Err(ScanError {
+                    kind: ScanErrorKind::UnmatchedDelimiter,
+                    position: token.position,
+                    message: format!("'{}' has nothing to close", token.lexeme),
+                })
+            }
+
+            tokens.push(token);
+            if complete {
+                return Ok((tokens, scanner.offset))
+            }
+        }
+
+        Err(ScanError {
+            kind: ScanErrorKind::Incomplete,
+            position: scanner.position,
+            message: "needs more input".to_owned(),
+        })
+    }
+
+    // treats the source as text with code embedded between the template
+    // delimiters. only the code is scanned; everything around it comes back
+    // as RawText tokens and the delimiters themselves are dropped.
+    pub fn scan_template(&self, source: &str) -> Result<Tokens, ScanError> {
+        let (ref open, ref close) = self.options.template_delimiters;
+        let tab_width = self.options.tab_width;
+        let mut tokens = Vec::new();
+        let mut position = START;
+        let mut rest = source;
+
+        while !rest.is_empty() {
+            let (raw, template) = rest.split_at(rest.find(open.as_str()).unwrap_or(rest.len()));
+            if !raw.is_empty() {
+                let start = (position.0, position.1 + 1);
+                position = advance_position(position, raw, tab_width);
+                let end = (position.0, position.1 + 1);
+                let mut token = Token::new(TT::RawText, raw.to_owned(), None, (start, end));
+                let offset = source.len() - rest.len();
+                token.offsets = (offset, offset + raw.len());
+                tokens.push(token);
+            }
+            if template.is_empty() {
+                break
+            }
+
+            position = advance_position(position, open, tab_width);
+            let code = &template[open.len()..];
+            let code = match code.find(close.as_str()) {
+                Some(end) => &code[..end],
+                None => return Err(ScanError {
+                    kind: ScanErrorKind::UnterminatedTemplate,
+                    position,
+                    message: format!("{} is never closed with {}", open, close),
+                }),
+            };
+
+            let mut scanner = self.scanner(code);
+            scanner.position = position;
+            scanner.base_offset = source.len() - template.len() + open.len();
+            // only the template as a whole ends in an Eof
+            scanner.emit_eof = false;
+            for scan in scanner {
+                tokens.push(scan?);
+            }
+
+            position = advance_position(advance_position(position, code, tab_width), close, tab_width);
+            rest = &template[open.len() + code.len() + close.len()..];
+        }
+
+        let mut eof = Token::synthetic(TT::Eof, String::new(), (position.0, position.1 + 1));
+        eof.offsets = (source.len(), source.len());
+        tokens.push(eof);
+        Ok(tokens)
+    }
+
+    // reads the source a chunk at a time rather than all at once.
+    // a character split between two reads is held until the rest of it arrives.
+    pub fn scan_reader<R: Read>(&self, mut reader: R) -> Result<Tokens, ScanError> {
+        let mut incremental = self.incremental();
+        let mut tokens = Vec::new();
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let mut pending = Vec::new();
+
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(incremental.unreadable(e.to_string())),
+            };
+            pending.extend_from_slice(&chunk[..read]);
+
+            let valid = match str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                Err(ref e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(e) => return Err(incremental.unreadable(e.to_string())),
+            };
+            incremental.feed(str::from_utf8(&pending[..valid]).unwrap());
+            pending.drain(..valid);
+
+            for scan in incremental.drain() {
+                tokens.push(scan?);
+            }
+        }
+
+        if !pending.is_empty() {
+            return Err(incremental.unreadable("source ends partway through a character".to_owned()))
+        }
+        for scan in incremental.finish() {
+            tokens.push(scan?);
+        }
+
+        Ok(tokens)
+    }
+
+    pub fn tokens<'a>(&'a self, source: &'a str) -> TokenStream<'a> {
+        TokenStream { scanner: self.scanner(source) }
+    }
+
+    pub fn incremental(&self) -> IncrementalScanner<'_> {
+        IncrementalScanner {
+            context: self,
+            buffer: String::new(),
+            position: START,
+            consumed: 0,
+        }
+    }
+
+    fn scanner<'a>(&'a self, source: &'a str) -> Scanner<'a> {
+        let reserved_words = self.reserved_words.as_ref().unwrap_or_else(|| default_reserved_words());
+        Scanner::new(source, reserved_words, self.interner.as_ref(), &self.options)
+    }
+}
+
+// one shared copy of each name handed to it
+#[derive(Debug,Default)]
+struct Interner {
+    names: Mutex<HashSet<Arc<str>>>,
+}
+
+impl Interner {
+    fn intern(&self, name: &str) -> Arc<str> {
+        // nothing can be left half done while the lock is held,
+        // so a panic elsewhere doesn't matter here
+        let mut names = self.names.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(interned) = names.get(name) {
+            return interned.clone()
+        }
+
+        let interned: Arc<str> = Arc::from(name);
+        names.insert(interned.clone());
+        interned
+    }
+}
+
+// a scan that hasn't happened yet; each token is read as it's asked for
+pub struct TokenStream<'a> {
+    scanner: Scanner<'a>,
+}
+
+impl<'a> iter::Iterator for TokenStream<'a> {
+    type Item = Scan;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.scanner.next()
+    }
+}
+
+// a guess at how many tokens a source holds, so the vec holding them isn't
+// reallocated over and over. with whitespace counted, tokens tend to take up
+// a few bytes each.
+fn expected_tokens(source: &str) -> usize {
+    source.len() / 4
+}
+
+// the furthest past the end of a token the scanner ever peeks
+const MAX_LOOKAHEAD: usize = 3;
+
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+// a scanner that is handed its input a chunk at a time.
+// tokens are only given back once we're sure the next chunk can't extend them.
+pub struct IncrementalScanner<'a> {
+    context: &'a ScanContext,
+    buffer: String,
+    position: Position,
+    // bytes already scanned and dropped from the front of the buffer
+    consumed: usize,
+}
+
+impl<'a> IncrementalScanner<'a> {
+    pub fn feed(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+    }
+
+    pub fn drain(&mut self) -> Vec<Scan> {
+        self.scan_buffer(false)
+    }
+
+    pub fn finish(&mut self) -> Vec<Scan> {
+        self.scan_buffer(true)
+    }
+
+    fn unreadable(&self, message: String) -> ScanError {
+        ScanError {
+            kind: ScanErrorKind::Unreadable,
+            position: self.position,
+            message,
+        }
+    }
+
+    fn scan_buffer(&mut self, finishing: bool) -> Vec<Scan> {
+        let mut scans = Vec::new();
+        // what's been scanned inside an interpolated string; it depends
+        // on the rest of the string, so it waits until that's closed.
+        let mut pending = Vec::new();
+        let (consumed, position) = {
+            let mut scanner = self.context.scanner(&self.buffer);
+            scanner.position = self.position;
+            scanner.base_offset = self.consumed;
+            let mut checkpoint = (0, self.position);
+
+            loop {
+                let scan = match scanner.next() {
+                    None => break,
+                    Some(scan) => scan,
+                };
+                // anything that got close enough to the end of the buffer for
+                // the scanner to have peeked past it might still continue
+                // in the next chunk (`12` then `34`), so it waits for that.
+                let rest = self.buffer[scanner.offset..].chars().count();
+                if !finishing && rest < MAX_LOOKAHEAD {
+                    break
+                }
+                pending.push(scan);
+                if scanner.interpolations.is_empty() {
+                    scans.append(&mut pending);
+                    checkpoint = (scanner.offset, scanner.position);
+                }
+            }
+
+            if finishing {
+                scans.append(&mut pending);
+                (self.buffer.len(), scanner.position)
+            } else {
+                checkpoint
+            }
+        };
+
+        self.buffer.drain(..consumed);
+        self.consumed += consumed;
+        self.position = position;
+        scans
+    }
 }
 
 type Tokens = Vec<Token>;
 
+#[derive(Debug)]
+pub struct ScanResult {
+    pub tokens: Tokens,
+    // byte offset into the source of the start of each line
+    pub line_offsets: Vec<usize>,
+    pub errors: Vec<ScanError>,
+    // things that scanned fine but probably aren't what was meant
+    pub warnings: Vec<ScanError>,
+    // what columns were counted with, to find them in a line again
+    tab_width: Column,
+}
+
+impl ScanResult {
+    pub fn line<'s>(&self, source: &'s str, line: Line) -> Option<&'s str> {
+        let line = (line as usize).checked_sub(1)?;
+        let start = *self.line_offsets.get(line)?;
+        let end = self.line_offsets.get(line + 1).cloned().unwrap_or(source.len());
+        source.get(start..end).map(|l| l.trim_end_matches(&['\n', '\r'][..]))
+    }
+
+    // the line `span` starts on, with the span underlined rustc style:
+    // a caret under its first character and tildes across the rest, like
+    //     x >= 1
+    //       ^~
+    // a span that carries on past its first line is underlined to the end
+    // of that line and finished with `...`.
+    pub fn underline(&self, source: &str, span: (Position, Position)) -> Option<String> {
+        let ((line, start), (end_line, end)) = span;
+        let text = self.line(source, line)?;
+        let mut marks = String::new();
+        let mut started = false;
+        let mut column = 0;
+
+        for c in text.chars() {
+            let first = column + 1;
+            column += if c == '\t' { self.tab_width } else { 1 };
+
+            if first < start {
+                // tabs are kept so the marks line up however they're shown
+                marks.push(if c == '\t' { '\t' } else { ' ' });
+            } else if end_line > line || first < end {
+                marks.push(if started { '~' } else { '^' });
+                started = true;
+            } else {
+                break
+            }
+        }
+
+        // nothing to stand under, like the end of the source: point at where it would be
+        if !started {
+            marks.extend(iter::repeat_n(' ', start.saturating_sub(column + 1) as usize));
+            marks.push('^');
+        }
+        if end_line > line {
+            marks.push_str("...");
+        }
+
+        Some(format!("{}\n{}", text, marks))
+    }
+}
+
 type Line = u64;
 type Column = u64;
 type Position = (Line, Column);
 
+// lines count from 1 like an editor's do. a column is how many characters
+// of its line have been read, so the first character sits in column 1 and
+// column 0 means the start of a line before anything is read.
+// tokens are placed at their first character.
+const START: Position = (1, 0);
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ScanErrorKind {
+    UnexpectedCharacter,
+    InvalidLiteral,
+    InvalidIdentifier,
+    InvalidSuffix,
+    TooManyErrors,
+    Incomplete,
+    UnmatchedDelimiter,
+    UnterminatedTemplate,
+    UnterminatedComment,
+    UnterminatedString,
+    // a `'` with nothing closing it on its line
+    UnterminatedChar,
+    // the source itself couldn't be read, or wasn't utf-8
+    Unreadable,
+    // only ever a warning
+    PrecisionLoss,
+}
+
 #[derive(Debug)]
 pub struct ScanError {
+    kind: ScanErrorKind,
     position: Position,
     message: String,
 }
 
+impl ScanError {
+    pub fn kind(&self) -> ScanErrorKind {
+        self.kind
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.kind == ScanErrorKind::PrecisionLoss
+    }
+}
+
 impl Error for ScanError {}
 
 impl Display for ScanError {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "Error reading code at line {}, column {}: {}",
-               self.position.0, self.position.1, self.message)
+        let severity = if self.is_warning() { "Warning" } else { "Error" };
+        write!(f, "{} reading code at line {}, column {}: {}",
+               severity, self.position.0, self.position.1, self.message)
+    }
+}
+
+// a keyword the scanner could never produce, because it wouldn't
+// scan as an identifier in the first place
+#[derive(Debug)]
+pub struct InvalidKeyword {
+    keyword: String,
+}
+
+impl Error for InvalidKeyword {}
+
+impl Display for InvalidKeyword {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:?} is not a valid keyword: keywords must be shaped like identifiers", self.keyword)
     }
 }
 
 type ScanSource<'a> = iter::Peekable<Chars<'a>>;
 
 struct Scanner<'a> {
+    // all of the text, for looking further ahead than `source` can
+    text: &'a str,
     source: ScanSource<'a>,
     position: Position,
+    // where the token being scanned began
+    start: Position,
+    // bytes of source read so far
+    offset: usize,
+    // the offset the token being scanned began at
+    start_offset: usize,
+    // where the text being scanned sits in the whole source,
+    // for when it's only a piece of it
+    base_offset: usize,
     current: String,
-    reserved_words: HashMap<&'a str, TT>,
+    reserved_words: &'a HashMap<&'static str, TT>,
+    interner: Option<&'a Interner>,
+    options: &'a Options,
+    // set when the caller is collecting errors rather than stopping at the first
+    recovering: bool,
+    warnings: Vec<ScanError>,
+    // cleared once the closing Eof token has been handed out
+    emit_eof: bool,
+    // for each `${` still open, how many braces deep into it we are,
+    // where it was opened, and where the string around it began
+    interpolations: Vec<(usize, Position, Position)>,
 }
 
 impl<'a> Scanner<'a> {
-    fn new(chars: ScanSource<'a>) -> Self {
+    fn new(text: &'a str,
+           reserved_words: &'a HashMap<&'static str, TT>,
+           interner: Option<&'a Interner>,
+           options: &'a Options) -> Self {
         Scanner {
-            source: chars,
-            position: (0, 0),
+            text,
+            source: text.chars().peekable(),
+            position: START,
+            start: START,
+            offset: 0,
+            start_offset: 0,
+            base_offset: 0,
             current: String::new(),
-            reserved_words: reserved_words(),
+            reserved_words,
+            interner,
+            options,
+            recovering: false,
+            warnings: Vec::new(),
+            emit_eof: true,
+            interpolations: Vec::new(),
+        }
+    }
+
+    fn eof(&mut self) -> Option<Scan> {
+        if let Some((_, open, _)) = self.interpolations.first().cloned() {
+            self.interpolations.clear();
+            return some_err(ScanError {
+                kind: ScanErrorKind::UnterminatedString,
+                position: open,
+                message: format!("Unterminated interpolation starting at line {}, column {}",
+                                 open.0, open.1),
+            })
+        }
+
+        if !self.emit_eof {
+            return None
         }
+
+        self.emit_eof = false;
+        let mut eof = Token::synthetic(TT::Eof, String::new(), self.start);
+        eof.offsets = self.offsets();
+        Some(Ok(eof))
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let ch = self.source.next();
+        self.offset += ch.map(char::len_utf8).unwrap_or(0);
+        ch
+    }
+
+    // the character `n` past the next one, without reading anything
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.text[self.offset..].chars().nth(n)
     }
 
     fn forward(&mut self) {
@@ -60,45 +777,85 @@ impl<'a> Scanner<'a> {
         self.position = (self.position.0 + 1, 0);
     }
 
-    fn skip_forward(&mut self) -> Option<Scan> {
-        self.forward();
-        self.next()
-    }
-
-    fn skip_down(&mut self) -> Option<Scan> {
-        self.down();
-        self.next()
+    // `\n`, `\r\n` and an old mac style `\r` all end a line exactly once
+    fn move_past(&mut self, ch: char) {
+        match ch {
+            '\n' => self.down(),
+            '\r' if self.source.peek() == Some(&'\n') => (),
+            '\r' => self.down(),
+            '\t' => self.position.1 += self.options.tab_width,
+            _ => self.forward(),
+        }
     }
 
     fn consume(&mut self, ch: char) {
-        if ch == '\n' {
-            self.down();
-        } else {
-            self.forward();
-        }
+        self.move_past(ch);
         self.current.push(ch);
     }
 
-    fn token(&self, tt: TT) -> Token {
-        let lexeme = self.current.trim();
+    fn offsets(&self) -> (usize, usize) {
+        (self.base_offset + self.start_offset, self.base_offset + self.offset)
+    }
+
+    fn token(&self, tt: TT) -> Scan {
+        // whitespace between tokens never makes it into `current`, so whatever
+        // whitespace is there belongs to the lexeme, like the inside of a string
+        let lexeme = &self.current;
+        let span = (self.start, (self.position.0, self.position.1 + 1));
+        // only tokens that stand for a value get a literal
+        let parsed = match tt {
+            // `_` only separates digits for the reader's benefit
+            TT::Number => Some(Lit::number(&lexeme.replace('_', ""))),
+            TT::String => Some(Lit::string(lexeme)),
+            TT::Char => Some(Lit::character(lexeme)),
+            TT::True => Some(Ok(Lit::Boolean(true))),
+            TT::False => Some(Ok(Lit::Boolean(false))),
+            TT::Nil => Some(Ok(Lit::Nil)),
+            _ => None,
+        };
         // the clones here make me think i should bite the bullet
         // and add lifetimes and make current a &mut str...
-        match Lit::from_str(lexeme) {
-            Ok(lit) => Token::new(tt, lexeme.to_owned(), Some(lit), self.position),
-            Err(_) => Token::new(tt, lexeme.to_owned(), None, self.position),
-        }
+        let lexeme: Arc<str> = match self.interner {
+            Some(interner) if tt == TT::Identifier => interner.intern(lexeme),
+            _ => Arc::from(lexeme.as_str()),
+        };
+        let token = match parsed {
+            Some(Ok(lit)) => Token::new(tt, lexeme, Some(lit), span),
+            // a literal that won't parse is a mistake in the source,
+            // so it needs to reach the user along with where it happened.
+            Some(Err(e)) => return Err(ScanError {
+                kind: ScanErrorKind::InvalidLiteral,
+                position: self.start,
+                message: e.to_string(),
+            }),
+            None => Token::new(tt, lexeme, None, span),
+        };
+
+        Ok(Token { offsets: self.offsets(), ..token })
     }
 
-    fn emit(&mut self, tt: TT) -> Token {
+    fn emit(&mut self, tt: TT) -> Scan {
         let tok = self.token(tt);
         self.current = String::new();
         tok
     }
 
-    fn unexpected_error(&self) -> ScanError {
+    // gives up on the lexeme in progress, and points at where it began
+    fn error(&mut self, kind: ScanErrorKind, message: String) -> ScanError {
+        self.current = String::new();
         ScanError {
-            position: self.position,
-            message: format!("Unexpected character: {:?}", self.current),
+            kind,
+            position: self.start,
+            message,
+        }
+    }
+
+    fn unexpected_error(&mut self) -> ScanError {
+        let current = mem::take(&mut self.current);
+        ScanError {
+            kind: ScanErrorKind::UnexpectedCharacter,
+            position: self.start,
+            message: format!("Unexpected character: {:?}", current),
         }
     }
 
@@ -109,78 +866,407 @@ impl<'a> Scanner<'a> {
             } else {
                 None
             })
-            .and_then(|_| self.source.next().map(|c| c))
+            .and_then(|_| self.advance())
     }
 
-    fn digest(&mut self, mc: char, emission: TT) -> Token {
+    fn digest(&mut self, mc: char, emission: TT) -> Scan {
         self.consume(mc);
         self.emit(emission)
     }
 
-    fn skip_til(&mut self, stop: char) {
-        loop {
-            if self.source.next().map(|c| c == stop).unwrap_or(true) {
-                break
-            } else {
-                self.forward();
-            }
+    // stops short of the line break, which gets scanned as usual
+    fn skip_rest_of_line(&mut self) {
+        while let Some(c) = self.source.peek().cloned().filter(|&c| !is_line_break(c)) {
+            self.advance();
+            self.move_past(c);
         }
     }
 
-    fn skip_line(&mut self) -> Option<Scan> {
-        self.skip_til('\n');
-        self.skip_down()
+    fn whitespace(&mut self, ch: char) -> Scan {
+        self.consume(ch);
+        self.slurp_while(&is_whitespace);
+        self.emit(TT::Whitespace)
     }
 
-    fn slurp_til(&mut self, stop: &Fn(char) -> bool) {
-        loop {
-            if self.source.peek().map(|&c| stop(c)).unwrap_or(true) {
-                break
-            } else {
-                match self.source.next() {
-                    None => break,
-                    Some(c) => self.consume(c),
-                }
-            }
+    fn line_comment(&mut self) -> Option<Scan> {
+        if !self.options.keep_comments {
+            self.skip_rest_of_line();
+            return None
         }
+
+        // the `//` that got us here
+        self.consume('/');
+        self.consume('/');
+        self.slurp_til(&is_line_break);
+        Some(self.emit(TT::Comment))
     }
 
-    fn slurp_while(&mut self, keep_going: &Fn(char) -> bool) {
-        loop {
-            if self.source.peek().map(|&c| !keep_going(c)).unwrap_or(false) {
-                break
+    fn block_comment(&mut self) -> Option<Scan> {
+        let start = self.start;
+        let keep = self.options.keep_comments;
+        // the `/*` that got us here
+        if keep {
+            self.consume('/');
+            self.consume('*');
+        } else {
+            self.forward();
+            self.forward();
+        }
+
+        // comments nest, so `/* a /* b */ c */` is all one comment.
+        // once a delimiter is matched its characters are used up,
+        // which keeps something like `/*/` from counting twice.
+        let mut depth = 1;
+        let mut previous = None;
+        while let Some(c) = self.advance() {
+            if keep {
+                self.consume(c);
             } else {
-                match self.source.next() {
-                    None => break,
-                    Some(c) => self.consume(c),
-                }
+                self.move_past(c);
             }
+
+            match (previous, c) {
+                (Some('/'), '*') => {
+                    depth += 1;
+                    previous = None;
+                },
+                (Some('*'), '/') => {
+                    depth -= 1;
+                    previous = None;
+                    if depth == 0 {
+                        break
+                    }
+                },
+                _ => previous = Some(c),
+            }
+        }
+
+        if depth > 0 {
+            self.current = String::new();
+            return some_err(ScanError {
+                kind: ScanErrorKind::UnterminatedComment,
+                position: start,
+                message: "Unterminated block comment".to_owned(),
+            })
+        }
+
+        if keep {
+            Some(self.emit(TT::Comment))
+        } else {
+            None
         }
     }
 
-    fn identifier(&mut self, ch: char) -> Token {
+    fn slurp_til(&mut self, stop: &dyn Fn(char) -> bool) {
+        loop {
+            if self.source.peek().map(|&c| stop(c)).unwrap_or(true) {
+                break
+            } else {
+                match self.advance() {
+                    None => break,
+                    Some(c) => self.consume(c),
+                }
+            }
+        }
+    }
+
+    fn slurp_while(&mut self, keep_going: &dyn Fn(char) -> bool) {
+        loop {
+            if self.source.peek().map(|&c| !keep_going(c)).unwrap_or(false) {
+                break
+            } else {
+                match self.advance() {
+                    None => break,
+                    Some(c) => self.consume(c),
+                }
+            }
+        }
+    }
+
+    fn identifier(&mut self, ch: char) -> Scan {
         self.consume(ch);
         self.slurp_while(&is_alphanumeric);
         let tt = self.reserved_words
             .get(&*self.current)
-            .map(|&tt| tt)
+            .copied()
             .unwrap_or(TT::Identifier);
         self.emit(tt)
     }
 
-    fn number(&mut self, ch: char) -> Token {
+    // a number's lexeme never includes a sign:
+    // `-5` and `+5` are an operator and then a number, for the parser to put together
+    fn number(&mut self, ch: char) -> Scan {
         self.consume(ch);
-        self.slurp_while(&is_digit);
-        match self.source.clone().take(2).collect::<Vec<char>>().get(0..2) {
-            Some(&[c1, c2]) => if is_dot(c1) && is_digit(c2) {
-                let dot = self.source.next().unwrap();
-                self.consume(dot);
-                self.slurp_while(&is_digit);
+
+        let prefix = if ch == '0' {
+            self.taste('x').or_else(|| self.taste('X'))
+                .or_else(|| self.taste('b'))
+                .or_else(|| self.taste('B'))
+        } else {
+            None
+        };
+
+        let radix = match prefix {
+            Some(p) => self.radix_digits(p)?,
+            None => {
+                self.digits()?;
+                // a number like `.5` has already had its fraction
+                let fraction = self.peek_nth(0).map(is_dot).unwrap_or(false)
+                    && self.peek_nth(1).map(is_digit).unwrap_or(false);
+                if !is_dot(ch) && fraction {
+                    let dot = self.advance().unwrap();
+                    self.consume(dot);
+                    self.digits()?;
+                }
+                self.exponent()?;
+                10
             },
-            _ => (),
         };
+
+        let suffixed = self.source.peek().map(|&c| is_alpha(c)).unwrap_or(false);
+
+        if suffixed && !self.options.number_suffixes.is_empty() {
+            return self.number_suffix()
+        }
+
+        if suffixed && self.options.strict_identifiers {
+            self.slurp_while(&is_alphanumeric);
+            let message = format!("identifiers cannot start with a digit: '{}'", self.current);
+            return Err(self.error(ScanErrorKind::InvalidIdentifier, message))
+        }
+
+        let integer = radix == 10 && !self.current.contains(&['.', 'e', 'E'][..]);
+        if integer && !fits_integer(&self.current) {
+            self.warnings.push(ScanError {
+                kind: ScanErrorKind::PrecisionLoss,
+                position: self.start,
+                message: format!("{} is too large for an integer and may lose precision as a float",
+                                 self.current),
+            });
+        }
+
         self.emit(TT::Number)
     }
+
+    // an optional `e10`, `E-3`, etc. on the end of a decimal number
+    fn exponent(&mut self) -> Result<(), ScanError> {
+        let digits_at = match (self.peek_nth(0), self.peek_nth(1)) {
+            (Some('e'), Some('+')) | (Some('e'), Some('-')) |
+            (Some('E'), Some('+')) | (Some('E'), Some('-')) => 2,
+            (Some('e'), _) | (Some('E'), _) => 1,
+            _ => return Ok(()),
+        };
+
+        match self.peek_nth(digits_at) {
+            Some(c) if is_digit(c) => (),
+            // something like `1else` was never meant to be an exponent
+            Some(c) if digits_at == 1 && is_alpha(c) => return Ok(()),
+            _ => {
+                for _ in 0..digits_at {
+                    let c = self.advance().unwrap();
+                    self.consume(c);
+                }
+                let message = format!("exponent with no digits in '{}'", self.current);
+                return Err(self.error(ScanErrorKind::InvalidLiteral, message))
+            },
+        }
+
+        for _ in 0..digits_at {
+            let c = self.advance().unwrap();
+            self.consume(c);
+        }
+        self.digits()
+    }
+
+    // a run of decimal digits, which may be broken up like `1_000_000`.
+    // the run always starts on a digit, so only trailing and doubled
+    // underscores need checking.
+    fn digits(&mut self) -> Result<(), ScanError> {
+        let start = self.current.len();
+        self.slurp_while(&|c| is_digit(c) || c == '_');
+
+        let run = &self.current[start..];
+        if !(run.ends_with('_') || run.contains("__")) {
+            return Ok(())
+        }
+
+        let message = format!("'_' must sit between two digits in '{}'", self.current);
+        Err(self.error(ScanErrorKind::InvalidLiteral, message))
+    }
+
+    // the digits of a `0x` or `0b` literal, once the prefix has been tasted
+    fn radix_digits(&mut self, prefix: char) -> Result<u32, ScanError> {
+        let (radix, name): (u32, &str) = match prefix {
+            'x' | 'X' => (16, "hexadecimal"),
+            _ => (2, "binary"),
+        };
+        self.consume(prefix);
+        self.slurp_while(&|c| c.is_digit(radix));
+
+        let message = match self.source.peek() {
+            // `0b12` is a mistake, not `0b1` followed by `2`
+            Some(&c) if is_digit(c) => format!("invalid digit '{}' in {} literal '{}'", c, name, self.current),
+            _ if self.current.len() == 2 => format!("{} literal '{}' has no digits", name, self.current),
+            _ => return Ok(radix),
+        };
+
+        self.slurp_while(&is_alphanumeric);
+        Err(self.error(ScanErrorKind::InvalidLiteral, message))
+    }
+
+    // raw strings leave backslashes and `${` alone
+    fn string(&mut self, quote: char, raw: bool) -> Scan {
+        self.consume(quote);
+        let began = self.start;
+        self.string_contents(quote, raw, TT::String, TT::InterpStart, began)
+    }
+
+    // scans up to the closing quote, which makes a `closed` token,
+    // or up to a `${`, which makes an `opened` one.
+    // `began` is where the whole string started, which for the pieces
+    // after an interpolation is well before the piece itself.
+    fn string_contents(&mut self, quote: char, raw: bool, closed: TT, opened: TT, began: Position) -> Scan {
+        let start = (self.source.clone(), self.position, self.offset);
+        let mut text = String::new();
+
+        loop {
+            let c = match self.advance() {
+                Some(c) => c,
+                None => return Err(self.unterminated_string(start, began)),
+            };
+            self.consume(c);
+
+            if c == quote {
+                return self.string_token(closed, text)
+            } else if raw {
+                text.push(c);
+            } else if c == '\\' {
+                // whatever is escaped stays in the string, even a quote or a `${`.
+                // making sense of it is up to the literal.
+                let escaped = match self.advance() {
+                    Some(escaped) => escaped,
+                    None => return Err(self.unterminated_string(start, began)),
+                };
+                self.consume(escaped);
+                text.push(c);
+                text.push(escaped);
+            } else if c == '$' && self.source.peek() == Some(&'{') {
+                let open = self.position;
+                let brace = self.advance().unwrap();
+                self.consume(brace);
+                self.interpolations.push((0, open, began));
+                return self.string_token(opened, text)
+            } else {
+                text.push(c);
+            }
+        }
+    }
+
+    // the lexeme has the quotes and braces, the literal is just the text
+    fn string_token(&mut self, tt: TT, text: String) -> Scan {
+        // a whole string gets its literal from the lexeme like anything else
+        if let TT::String = tt {
+            return self.emit(tt)
+        }
+
+        let literal = Lit::unescaped(&text);
+        let mut token = self.emit(tt)?;
+        match literal {
+            Ok(lit) => {
+                token.literal = Some(lit);
+                Ok(token)
+            },
+            Err(e) => Err(ScanError {
+                kind: ScanErrorKind::InvalidLiteral,
+                position: token.position,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    fn unterminated_string(&mut self, start: (ScanSource<'a>, Position, usize), began: Position) -> ScanError {
+        self.current = String::new();
+        let error = ScanError {
+            kind: ScanErrorKind::UnterminatedString,
+            position: began,
+            message: format!("Unterminated string starting at line {}, column {}",
+                             began.0, began.1),
+        };
+        if self.recovering {
+            // a missing close quote would otherwise eat the rest
+            // of the file, so go back and only give up the line
+            // the string opened on.
+            self.source = start.0;
+            self.position = start.1;
+            self.offset = start.2;
+            self.skip_rest_of_line();
+        }
+        error
+    }
+
+    // a single, possibly escaped, character between single quotes
+    fn character(&mut self, quote: char) -> Scan {
+        self.consume(quote);
+
+        let mut bad_escape = false;
+        match self.source.peek().cloned() {
+            Some('\\') => {
+                self.advance();
+                self.consume('\\');
+                if let Some(c) = self.source.peek().cloned().filter(|&c| !is_line_break(c)) {
+                    self.advance();
+                    self.consume(c);
+                    bad_escape = !is_escape(c);
+                }
+            },
+            Some('\'') => {
+                self.advance();
+                self.consume(quote);
+                return Err(self.error(ScanErrorKind::InvalidLiteral, "empty character literal ''".to_owned()))
+            },
+            Some(c) if !is_line_break(c) => {
+                self.advance();
+                self.consume(c);
+            },
+            _ => (),
+        }
+
+        if !bad_escape {
+            if let Some(c) = self.taste(quote) {
+                return self.digest(c, TT::Char)
+            }
+        }
+
+        // take the rest of something like `'ab'` so it doesn't get scanned again
+        self.slurp_til(&|c| c == quote || is_line_break(c));
+        let (kind, problem) = match self.taste(quote) {
+            Some(c) => {
+                self.consume(c);
+                let problem = if bad_escape { "has an unknown escape" } else { "must hold exactly one character" };
+                (ScanErrorKind::InvalidLiteral, problem)
+            },
+            None => (ScanErrorKind::UnterminatedChar, "is never closed"),
+        };
+        let message = format!("character literal {} {}", self.current, problem);
+        Err(self.error(kind, message))
+    }
+
+    fn number_suffix(&mut self) -> Scan {
+        let digits = self.current.len();
+        self.slurp_while(&is_alphanumeric);
+        let suffix = self.current.split_off(digits);
+
+        if !self.options.number_suffixes.contains(&suffix) {
+            let message = format!("unknown number suffix '{}' in '{}{}'", suffix, self.current, suffix);
+            return Err(self.error(ScanErrorKind::InvalidSuffix, message))
+        }
+
+        // the value comes from the digits alone, the suffix just rides along
+        let mut token = self.emit(TT::Number)?;
+        token.lexeme = format!("{}{}", token.lexeme, suffix).into();
+        token.suffix = Some(suffix);
+        Ok(token)
+    }
 }
 
 type Scan = Result<Token, ScanError>;
@@ -189,104 +1275,207 @@ impl<'a> iter::Iterator for Scanner<'a> {
     type Item = Scan;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.source.next().and_then(|ch| {
-            // this feels like maybe there could be more complex matching
-            // maybe some sort of "scan instruction" type?
-            match ch {
-                '(' => some_ok(self.digest(ch, TT::LeftParen)),
-                ')' => some_ok(self.digest(ch, TT::RightParen)),
-                '{' => some_ok(self.digest(ch, TT::LeftBrace)),
-                '}' => some_ok(self.digest(ch, TT::RightBrace)),
-                ',' => some_ok(self.digest(ch, TT::Comma)),
-                '.' => some_ok(self.digest(ch, TT::Dot)),
-                '-' => some_ok(self.digest(ch, TT::Minus)),
-                '+' => some_ok(self.digest(ch, TT::Plus)),
-                ';' => some_ok(self.digest(ch, TT::Semicolon)),
-                '*' => some_ok(self.digest(ch, TT::Star)),
-                '!' => {
-                    self.consume(ch);
-                    self.taste('=')
-                        .and_then(|nc| some_ok(self.digest(nc, TT::BangEqual)))
-                        .or_else(|| some_ok(self.emit(TT::Bang)))
-                },
-                '=' => {
-                    self.consume(ch);
-                    self.taste('=')
-                        .and_then(|nc| some_ok(self.digest(nc, TT::EqualEqual)))
-                        .or_else(|| some_ok(self.emit(TT::Equal)))
-                },
-                '<' => {
-                    self.consume(ch);
-                    self.taste('=')
-                        .and_then(|nc| some_ok(self.digest(nc, TT::LessEqual)))
-                        .or_else(|| some_ok(self.emit(TT::Less)))
-                },
-                '>' => {
+        // skipping things one at a time rather than recursing past them,
+        // so a long run of whitespace or comments can't overflow the stack
+        loop {
+            // whatever gets read next is the first character of the token
+            self.start = (self.position.0, self.position.1 + 1);
+            self.start_offset = self.offset;
+            let ch = match self.advance() {
+                Some(ch) => ch,
+                None => return self.eof(),
+            };
+            if let Some(scan) = self.scan_from(ch) {
+                return Some(scan)
+            }
+        }
+    }
+}
+
+impl<'a> Scanner<'a> {
+    // the token `ch` starts, or None when it only started
+    // something to skip over, like whitespace or a comment
+    fn scan_from(&mut self, ch: char) -> Option<Scan> {
+        // this feels like maybe there could be more complex matching
+        // maybe some sort of "scan instruction" type?
+        match ch {
+            '(' => Some(self.digest(ch, TT::LeftParen)),
+            ')' => Some(self.digest(ch, TT::RightParen)),
+            '{' => {
+                if let Some(&mut (ref mut depth, _, _)) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                Some(self.digest(ch, TT::LeftBrace))
+            },
+            '}' => match self.interpolations.last_mut() {
+                // closes a `${`, so the string picks back up
+                Some(&mut (0, _, began)) => {
+                    self.interpolations.pop();
                     self.consume(ch);
-                    self.taste('=')
-                        .and_then(|nc| some_ok(self.digest(nc, TT::GreaterEqual)))
-                        .or_else(|| some_ok(self.emit(TT::Greater)))
+                    Some(self.string_contents('"', false, TT::InterpEnd, TT::InterpPart, began))
                 },
-                '/' => {
-                    match self.taste('/') {
-                        Some(_) => self.skip_line(),
-                        None => some_ok(self.digest(ch, TT::Slash)),
-                    }
+                Some(&mut (ref mut depth, _, _)) => {
+                    *depth -= 1;
+                    Some(self.digest(ch, TT::RightBrace))
                 },
-
-                // strings
-                '"' => {
-                    self.consume(ch);
-                    self.slurp_til(&|c| c == '"');
-                    match self.source.next() {
-                        None => some_err(self.unexpected_error()),
-                        Some(c) => some_ok(self.digest(c, TT::String)),
-                    }
-                }
-
-                // whitespace
-                ' ' => self.skip_forward(),
-                '\r' => self.skip_forward(),
-                '\t' => self.skip_forward(),
-                '\n' => self.skip_down(),
-                _ => if is_digit(ch) {
-                    some_ok(self.number(ch))
-                } else if is_alpha(ch) {
-                    some_ok(self.identifier(ch))
+                None => Some(self.digest(ch, TT::RightBrace)),
+            },
+            ',' => Some(self.digest(ch, TT::Comma)),
+            '.' => match self.source.peek() {
+                Some(&c) if is_digit(c) => Some(self.number(ch)),
+                _ => Some(self.digest(ch, TT::Dot)),
+            },
+            '-' => Some(self.digest(ch, TT::Minus)),
+            '+' => Some(self.digest(ch, TT::Plus)),
+            ';' => Some(self.digest(ch, TT::Semicolon)),
+            '*' => Some(self.digest(ch, TT::Star)),
+            '%' => Some(self.digest(ch, TT::Percent)),
+            '?' => {
+                // there's no lone `?` operator, only `??`
+                self.consume(ch);
+                self.taste('?')
+                    .map(|nc| self.digest(nc, TT::QuestionQuestion))
+                    .or_else(|| some_err(self.unexpected_error()))
+            },
+            '!' => {
+                self.consume(ch);
+                self.taste('=')
+                    .map(|nc| self.digest(nc, TT::BangEqual))
+                    .or_else(|| Some(self.emit(TT::Bang)))
+            },
+            '=' => {
+                self.consume(ch);
+                self.taste('=')
+                    .map(|nc| self.digest(nc, TT::EqualEqual))
+                    .or_else(|| Some(self.emit(TT::Equal)))
+            },
+            '<' => {
+                self.consume(ch);
+                self.taste('=')
+                    .map(|nc| self.digest(nc, TT::LessEqual))
+                    .or_else(|| Some(self.emit(TT::Less)))
+            },
+            '>' => {
+                self.consume(ch);
+                self.taste('=')
+                    .map(|nc| self.digest(nc, TT::GreaterEqual))
+                    .or_else(|| Some(self.emit(TT::Greater)))
+            },
+            '/' => {
+                if self.taste('/').is_some() {
+                    self.line_comment()
+                } else if self.taste('*').is_some() {
+                    self.block_comment()
                 } else {
-                    some_err(self.unexpected_error())
+                    Some(self.digest(ch, TT::Slash))
                 }
+            },
+
+            '\'' => Some(self.character(ch)),
+
+            // strings
+            '"' => Some(self.string(ch, false)),
+            // `r"..."` is a raw string, any other `r` starts an identifier
+            'r' if self.source.peek() == Some(&'"') => {
+                self.consume(ch);
+                let quote = self.advance().unwrap();
+                Some(self.string(quote, true))
+            },
+
+            // whitespace
+            _ if self.options.keep_whitespace && is_whitespace(ch) => Some(self.whitespace(ch)),
+            ' ' |
+            '\u{000B}' | // vertical tab
+            '\u{000C}' | // form feed
+            '\t' | '\r' | '\n' => {
+                self.move_past(ch);
+                None
+            },
+            _ => if is_digit(ch) {
+                Some(self.number(ch))
+            } else if is_alpha(ch) {
+                Some(self.identifier(ch))
+            } else {
+                // use the character up so scanning can carry on after it
+                self.consume(ch);
+                some_err(self.unexpected_error())
             }
-        })
+        }
     }
 }
 
+// whole numbers too big for an i64 end up as f64s, which can't hold them exactly.
+// (ones in another base are an error instead)
+fn fits_integer(lexeme: &str) -> bool {
+    i64::from_str(&lexeme.replace('_', "")).is_ok()
+}
+
+// where the scanner would end up after reading `text` from `position`
+fn advance_position(position: Position, text: &str, tab_width: Column) -> Position {
+    let mut chars = text.chars().peekable();
+    let mut position = position;
+    while let Some(ch) = chars.next() {
+        position = match ch {
+            '\r' if chars.peek() == Some(&'\n') => position,
+            '\r' | '\n' => (position.0 + 1, 0),
+            '\t' => (position.0, position.1 + tab_width),
+            _ => (position.0, position.1 + 1),
+        };
+    }
+    position
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().map(is_alpha).unwrap_or(false) && chars.all(is_alphanumeric)
+}
+
+fn is_line_break(ch: char) -> bool {
+    ch == '\n' || ch == '\r'
+}
+
+fn is_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\r' | '\t' | '\n' | '\u{000B}' | '\u{000C}')
+}
+
+// identifiers follow unicode's XID rules, plus `_` as a first character
 fn is_alphanumeric(ch: char) -> bool {
-    is_alpha(ch) || is_digit(ch)
+    UnicodeXID::is_xid_continue(ch)
 }
 
 fn is_alpha(ch: char) -> bool {
-    ch.is_alphabetic() || ch == '_'
+    UnicodeXID::is_xid_start(ch) || ch == '_'
 }
 
 fn is_digit(ch: char) -> bool {
-    ch.is_digit(10)
+    ch.is_ascii_digit()
 }
 
-fn is_dot(ch: char) -> bool {
-    ch == '.'
+// what can follow a `\\` in a character literal
+fn is_escape(ch: char) -> bool {
+    "ntr0\\'\"".contains(ch)
 }
 
-fn some_ok<T, E>(x: T) -> Option<Result<T,E>> {
-    Some(Ok(x))
+fn is_dot(ch: char) -> bool {
+    ch == '.'
 }
 
 fn some_err<T,E>(x: E) -> Option<Result<T,E>> {
     Some(Err(x))
 }
 
-// in reality this should probably use lazy_static! or phf
-fn reserved_words<'a>() -> HashMap<&'a str, TT> {
+pub fn reserved_words() -> HashMap<&'static str, TT> {
+    default_reserved_words().clone()
+}
+
+// built the first time anything is scanned and shared from then on,
+// so short scans like repl lines don't each pay to fill a map.
+fn default_reserved_words() -> &'static HashMap<&'static str, TT> {
+    static RESERVED_WORDS: OnceLock<HashMap<&'static str, TT>> = OnceLock::new();
+    RESERVED_WORDS.get_or_init(build_reserved_words)
+}
+
+fn build_reserved_words() -> HashMap<&'static str, TT> {
     let mut rs = HashMap::new();
     rs.insert("and",    TT::And);
     rs.insert("class",  TT::Class);
@@ -306,3 +1495,488 @@ fn reserved_words<'a>() -> HashMap<&'a str, TT> {
     rs.insert("while",  TT::While);
     rs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(tokens: &[Token]) -> Vec<TT> {
+        tokens.iter().map(|t| t.token_type).collect()
+    }
+
+    fn lexemes(tokens: &[Token]) -> Vec<(TT, &str)> {
+        tokens.iter().map(|t| (t.token_type, &*t.lexeme)).collect()
+    }
+
+    #[test]
+    fn scan_all_caps_errors() {
+        let garbage = "@".repeat(50);
+        let (_, errors) = scan_all_capped(&garbage, 5);
+
+        assert_eq!(errors.len(), 6);
+        assert!(errors[..5].iter().all(|e| e.kind() == ScanErrorKind::UnexpectedCharacter));
+        assert_eq!(errors[5].kind(), ScanErrorKind::TooManyErrors);
+        assert_eq!(errors[5].position(), (1, 6));
+    }
+
+    #[test]
+    fn scan_all_reads_to_the_end_with_errors_up_to_the_cap() {
+        let (tokens, errors) = scan_all_capped("@ #\nx", 2);
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.kind() == ScanErrorKind::UnexpectedCharacter));
+        assert_eq!(types(&tokens), vec![TT::Identifier, TT::Eof]);
+    }
+
+    #[test]
+    fn scan_all_with_no_cap() {
+        let garbage = "@".repeat(500);
+        let (_, errors) = scan_all_capped(&garbage, 0);
+
+        assert_eq!(errors.len(), 500);
+    }
+
+    #[test]
+    fn overflowing_numbers_report_where_they_are() {
+        let error = scan("var x = 1e999;").unwrap_err();
+
+        assert_eq!(error.kind(), ScanErrorKind::InvalidLiteral);
+        assert_eq!(error.position(), (1, 9));
+        assert!(error.to_string().contains("1e999"));
+    }
+
+    #[test]
+    fn interned_identifiers_are_shared_across_documents() {
+        let context = ScannerBuilder::new().intern_identifiers(true).build();
+        let first = context.scan("var count = 1;").unwrap();
+        let second = context.scan("print count + other;").unwrap();
+        let third = context.scan("other = count;").unwrap();
+
+        assert!(Arc::ptr_eq(&first[1].lexeme, &second[1].lexeme));
+        assert!(Arc::ptr_eq(&first[1].lexeme, &third[2].lexeme));
+        assert!(Arc::ptr_eq(&second[3].lexeme, &third[0].lexeme));
+    }
+
+    #[test]
+    fn identifiers_are_not_interned_by_default() {
+        let context = ScanContext::new();
+        let first = context.scan("count").unwrap();
+        let second = context.scan("count").unwrap();
+
+        assert_eq!(first[0].lexeme, second[0].lexeme);
+        assert!(!Arc::ptr_eq(&first[0].lexeme, &second[0].lexeme));
+    }
+
+    #[test]
+    fn digits_then_letters_are_two_tokens_by_default() {
+        let tokens = scan("123abc").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![(TT::Number, "123"), (TT::Identifier, "abc"), (TT::Eof, "")]);
+    }
+
+    #[test]
+    fn digits_then_letters_are_an_error_in_strict_mode() {
+        let strict = ScannerBuilder::new().strict_identifiers(true).build();
+        let error = strict.scan("123abc").unwrap_err();
+
+        assert_eq!(error.kind(), ScanErrorKind::InvalidIdentifier);
+        assert!(error.to_string().contains("'123abc'"));
+    }
+
+    #[test]
+    fn suffixes_ride_along_on_numbers_when_configured() {
+        let context = ScannerBuilder::new().number_suffixes(&["i", "u8"]).build();
+        let tokens = context.scan("10i 255u8").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![(TT::Number, "10i"), (TT::Number, "255u8"), (TT::Eof, "")]);
+        assert_eq!(tokens[0].suffix.as_deref(), Some("i"));
+        assert_eq!(tokens[0].literal, Some(Lit::Integer(10)));
+        assert_eq!(tokens[1].suffix.as_deref(), Some("u8"));
+        assert_eq!(tokens[1].literal, Some(Lit::Integer(255)));
+    }
+
+    #[test]
+    fn unknown_suffixes_are_an_error_when_configured() {
+        let context = ScannerBuilder::new().number_suffixes(&["i"]).build();
+        let error = context.scan("10x").unwrap_err();
+
+        assert_eq!(error.kind(), ScanErrorKind::InvalidSuffix);
+    }
+
+    #[test]
+    fn suffixes_are_identifiers_by_default() {
+        let tokens = scan("10i").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![(TT::Number, "10"), (TT::Identifier, "i"), (TT::Eof, "")]);
+        assert_eq!(tokens[0].suffix, None);
+    }
+
+    #[test]
+    fn form_feeds_and_vertical_tabs_separate_tokens() {
+        let tokens = scan("a\u{000C}b\u{000B}c").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::Identifier, "a"), (TT::Identifier, "b"), (TT::Identifier, "c"), (TT::Eof, ""),
+        ]);
+        assert_eq!(tokens[1].position, (1, 3));
+    }
+
+    #[test]
+    fn first_statement_stops_at_a_top_level_semicolon() {
+        let source = "print 1; print 2;";
+        let (tokens, end) = scan_first_statement(source).unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![(TT::Print, "print"), (TT::Number, "1"), (TT::Semicolon, ";")]);
+        assert_eq!(&source[end..], " print 2;");
+    }
+
+    #[test]
+    fn first_statement_takes_a_whole_block() {
+        let source = "{ var x = (1); } rest";
+        let (tokens, end) = scan_first_statement(source).unwrap();
+
+        assert_eq!(tokens.len(), 9);
+        assert_eq!(tokens.last().unwrap().token_type, TT::RightBrace);
+        assert_eq!(&source[end..], " rest");
+    }
+
+    #[test]
+    fn first_statement_needs_more_input_when_nothing_is_complete() {
+        for partial in &["print 1", "{ print 1;", "print (1;", "", "print \"hello", "/* still typing",
+                         "print 'a", "print \"a ${b", "print \"one\ntwo"] {
+            let error = scan_first_statement(partial).unwrap_err();
+            assert_eq!(error.kind(), ScanErrorKind::Incomplete, "{:?}", partial);
+        }
+    }
+
+    #[test]
+    fn first_statement_rejects_a_stray_closing_delimiter() {
+        let error = scan_first_statement("print 1);").unwrap_err();
+        assert_eq!(error.kind(), ScanErrorKind::UnmatchedDelimiter);
+        assert_eq!(error.position(), (1, 8));
+
+        let error = scan_first_statement("} print 1;").unwrap_err();
+        assert_eq!(error.kind(), ScanErrorKind::UnmatchedDelimiter);
+        assert_eq!(error.position(), (1, 1));
+    }
+
+    #[test]
+    fn incremental_numbers_carry_on_into_the_next_chunk() {
+        let context = ScanContext::new();
+        let mut incremental = context.incremental();
+
+        incremental.feed("12");
+        let mut scans = incremental.drain();
+        incremental.feed("34 +");
+        scans.extend(incremental.drain());
+        scans.extend(incremental.finish());
+        let tokens: Vec<Token> = scans.into_iter().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![(TT::Number, "1234"), (TT::Plus, "+"), (TT::Eof, "")]);
+        assert_eq!(tokens[0].literal, Some(Lit::Integer(1234)));
+        assert_eq!(tokens[1].position, (1, 6));
+        assert_eq!(tokens[1].offsets, (5, 6));
+    }
+
+    #[test]
+    fn incremental_scans_match_a_whole_scan() {
+        let source = "var greeting = \"hi ${name}!\";\nprint greeting; // done\n";
+        let context = ScanContext::new();
+        let mut incremental = context.incremental();
+        let mut scans = Vec::new();
+
+        for chunk in source.as_bytes().chunks(3) {
+            incremental.feed(str::from_utf8(chunk).unwrap());
+            scans.extend(incremental.drain());
+        }
+        scans.extend(incremental.finish());
+        let tokens: Vec<Token> = scans.into_iter().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(tokens, scan(source).unwrap());
+    }
+
+    #[test]
+    fn question_marks_come_in_pairs() {
+        let tokens = scan("a ?? b").unwrap();
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::Identifier, "a"), (TT::QuestionQuestion, "??"), (TT::Identifier, "b"), (TT::Eof, ""),
+        ]);
+
+        let error = scan("a ? b").unwrap_err();
+        assert_eq!(error.kind(), ScanErrorKind::UnexpectedCharacter);
+        assert_eq!(error.position(), (1, 3));
+    }
+
+    #[test]
+    fn scanning_resumes_on_the_line_after_an_unterminated_string() {
+        let (tokens, errors) = scan_all("var s = \"oops;\nprint 1;\n");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), ScanErrorKind::UnterminatedString);
+        assert_eq!(errors[0].position(), (1, 9));
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::Var, "var"), (TT::Identifier, "s"), (TT::Equal, "="),
+            (TT::Print, "print"), (TT::Number, "1"), (TT::Semicolon, ";"), (TT::Eof, ""),
+        ]);
+        assert_eq!(tokens[3].position, (2, 1));
+    }
+
+    #[test]
+    fn an_unterminated_string_stops_a_plain_scan() {
+        let error = scan("var s = \"oops;\nprint 1;\n").unwrap_err();
+
+        assert_eq!(error.kind(), ScanErrorKind::UnterminatedString);
+    }
+
+    #[test]
+    fn custom_keywords_scan_alongside_the_defaults() {
+        let mut words = reserved_words();
+        words.insert("match", TT::Keyword);
+        words.remove("class");
+        let context = ScannerBuilder::new().reserved_words(words).unwrap().build();
+        let tokens = context.scan("match x if class").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::Keyword, "match"), (TT::Identifier, "x"), (TT::If, "if"), (TT::Identifier, "class"), (TT::Eof, ""),
+        ]);
+        assert!(tokens[0].is_keyword());
+    }
+
+    #[test]
+    fn custom_keywords_must_look_like_identifiers() {
+        for word in &["9lives", "", "two words", "a-b"] {
+            let mut words = reserved_words();
+            words.insert(word, TT::Keyword);
+            assert!(ScannerBuilder::new().reserved_words(words).is_err(), "{:?}", word);
+        }
+    }
+
+    #[test]
+    fn the_line_index_finds_the_line_an_error_is_on() {
+        let source = "var a = 1;\r\nvar b = @;\rprint b;\n";
+        let result = scan_indexed(source);
+        let (line, column) = result.errors[0].position();
+
+        assert_eq!(result.line_offsets, vec![0, 12, 23, 32]);
+        assert_eq!((line, column), (2, 9));
+        assert_eq!(result.line(source, line), Some("var b = @;"));
+        assert_eq!(&result.line(source, line).unwrap()[column as usize - 1..], "@;");
+        assert_eq!(result.line(source, 3), Some("print b;"));
+        assert_eq!(result.line(source, 0), None);
+        assert_eq!(result.line(source, 5), None);
+    }
+
+    #[test]
+    fn templates_scan_only_the_code_between_delimiters() {
+        let tokens = ScanContext::new().scan_template("Hello {{ name }}!").unwrap();
+        let spans: Vec<_> = tokens.iter().map(|t| (t.token_type, &*t.lexeme, t.span(), t.offsets)).collect();
+
+        assert_eq!(spans, vec![
+            (TT::RawText, "Hello ", ((1, 1), (1, 7)), (0, 6)),
+            (TT::Identifier, "name", ((1, 10), (1, 14)), (9, 13)),
+            (TT::RawText, "!", ((1, 17), (1, 18)), (16, 17)),
+            (TT::Eof, "", ((1, 18), (1, 18)), (17, 17)),
+        ]);
+    }
+
+    #[test]
+    fn templates_use_the_configured_delimiters() {
+        let context = ScannerBuilder::new().template_delimiters("<%", "%>").build();
+        let tokens = context.scan_template("a <% 1 + 2 %> {{ b }}").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::RawText, "a "), (TT::Number, "1"), (TT::Plus, "+"), (TT::Number, "2"),
+            (TT::RawText, " {{ b }}"), (TT::Eof, ""),
+        ]);
+
+        let error = context.scan_template("a <% 1").unwrap_err();
+        assert_eq!(error.kind(), ScanErrorKind::UnterminatedTemplate);
+    }
+
+    #[test]
+    fn integers_too_large_for_an_i64_warn() {
+        let result = scan_indexed("9223372036854775808");
+
+        assert!(result.errors.is_empty());
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].kind(), ScanErrorKind::PrecisionLoss);
+        assert!(result.warnings[0].is_warning());
+        assert_eq!(result.tokens[0].literal, Some(Lit::Number(9223372036854775808.0)));
+    }
+
+    #[test]
+    fn integers_that_fit_dont_warn() {
+        // 2^53, and the largest i64
+        let result = scan_indexed("9007199254740992 9223372036854775807 1_000");
+
+        assert!(result.warnings.is_empty());
+        assert_eq!(result.tokens[1].literal, Some(Lit::Integer(i64::MAX)));
+    }
+
+    #[test]
+    fn tokens_start_at_their_first_character() {
+        let tokens = scan("a >= foobar").unwrap();
+        let spans: Vec<_> = tokens.iter().map(|t| t.span()).collect();
+
+        assert_eq!(spans, vec![((1, 1), (1, 2)), ((1, 3), (1, 5)), ((1, 6), (1, 12)), ((1, 12), (1, 12))]);
+    }
+
+    #[test]
+    fn errors_point_at_the_start_of_their_token() {
+        let strict = ScannerBuilder::new().strict_identifiers(true).build();
+        let cases = vec![
+            (scan("x = 0x;"), (1, 5)),
+            (scan("x = 'ab';"), (1, 5)),
+            (scan("x = 1e;"), (1, 5)),
+            (scan("x = 1__0;"), (1, 5)),
+            (strict.scan("x = 123abc;"), (1, 5)),
+        ];
+
+        for (scanned, position) in cases {
+            assert_eq!(scanned.unwrap_err().position(), position);
+        }
+    }
+
+    #[test]
+    fn every_kind_of_line_break_ends_a_line_once() {
+        let tokens = scan("a\nb\r\nc\rd\r\n\re").unwrap();
+        let positions: Vec<_> = tokens.iter().map(|t| (&*t.lexeme, t.position)).collect();
+
+        assert_eq!(positions, vec![
+            ("a", (1, 1)), ("b", (2, 1)), ("c", (3, 1)), ("d", (4, 1)), ("e", (6, 1)), ("", (6, 2)),
+        ]);
+    }
+
+    #[test]
+    fn line_breaks_inside_tokens_are_counted_the_same() {
+        let tokens = scan("\"one\r\ntwo\rthree\" x /* a\r\nb */ y").unwrap();
+        let spans: Vec<_> = tokens.iter().map(|t| t.span()).collect();
+
+        assert_eq!(spans[0], ((1, 1), (3, 7)));
+        assert_eq!(spans[1], ((3, 8), (3, 9)));
+        assert_eq!(spans[2], ((4, 6), (4, 7)));
+    }
+
+    #[test]
+    fn the_line_index_agrees_with_the_scanner() {
+        let source = "a\nb\r\nc\rd";
+        let result = scan_indexed(source);
+
+        for token in result.tokens.iter().filter(|t| t.token_type == TT::Identifier) {
+            let line = result.line(source, token.position.0).unwrap();
+            assert_eq!(line, &*token.lexeme);
+        }
+    }
+
+    #[test]
+    fn interpolated_strings_scan_into_pieces() {
+        let tokens = scan("\"a ${b} c ${ {d} } e\"").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::InterpStart, "\"a ${"), (TT::Identifier, "b"), (TT::InterpPart, "} c ${"),
+            (TT::LeftBrace, "{"), (TT::Identifier, "d"), (TT::RightBrace, "}"),
+            (TT::InterpEnd, "} e\""), (TT::Eof, ""),
+        ]);
+        assert_eq!(tokens[2].literal, Some(Lit::String(" c ".to_owned())));
+    }
+
+    #[test]
+    fn unterminated_interpolated_strings_point_at_the_opening_quote() {
+        for source in &["\"abc ${ y } def", "x\n  \"abc ${ y } def ${ z } ghi", "\"abc ${ y } d\\"] {
+            let error = scan(source).unwrap_err();
+            let opened = if source.starts_with('x') { (2, 3) } else { (1, 1) };
+
+            assert_eq!(error.kind(), ScanErrorKind::UnterminatedString, "{:?}", source);
+            assert_eq!(error.position(), opened, "{:?}", source);
+            assert!(error.to_string().ends_with(&format!("starting at line {}, column {}", opened.0, opened.1)));
+        }
+    }
+
+    #[test]
+    fn a_dot_is_only_a_fraction_with_a_digit_after_it() {
+        let tokens = scan("3.14 3. 3.x").unwrap();
+
+        assert_eq!(lexemes(&tokens), vec![
+            (TT::Number, "3.14"), (TT::Number, "3"), (TT::Dot, "."),
+            (TT::Number, "3"), (TT::Dot, "."), (TT::Identifier, "x"), (TT::Eof, ""),
+        ]);
+        assert!(matches!(tokens[0].literal, Some(Lit::Number(_))));
+        assert_eq!(tokens[1].literal, Some(Lit::Integer(3)));
+    }
+
+    #[test]
+    fn the_capacity_hint_doesnt_change_the_tokens() {
+        // dense and sparse sources, so the guess is both too small and too big
+        let sources = vec![
+            "(){};,.-+*".repeat(50),
+            format!("var x = 1;{}print x;", " ".repeat(1000)),
+            String::new(),
+        ];
+
+        for source in sources {
+            let scanned = scan(&source).unwrap();
+            let streamed: Vec<Token> = tokens(&source).collect::<Result<_, _>>().unwrap();
+
+            assert_eq!(scanned, streamed);
+            assert!(scanned.capacity() >= expected_tokens(&source));
+        }
+    }
+
+    #[test]
+    fn strings_keep_their_padding() {
+        let tokens = scan("\"  padded\t \" \"\tx\"").unwrap();
+
+        assert_eq!(&*tokens[0].lexeme, "\"  padded\t \"");
+        assert_eq!(tokens[0].literal, Some(Lit::String("  padded\t ".to_owned())));
+        assert_eq!(tokens[0].span(), ((1, 1), (1, 13)));
+        assert_eq!(tokens[1].literal, Some(Lit::String("\tx".to_owned())));
+    }
+
+    #[test]
+    fn first_statement_reports_what_more_input_cant_fix() {
+        // a character literal ends with its line
+        let error = scan_first_statement("print 'a\n").unwrap_err();
+        assert_eq!(error.kind(), ScanErrorKind::UnterminatedChar);
+
+        let error = scan_first_statement("print 'ab';").unwrap_err();
+        assert_eq!(error.kind(), ScanErrorKind::InvalidLiteral);
+    }
+
+    #[test]
+    fn underlines_a_whole_token() {
+        let source = "x >= 1.5";
+        let result = scan_indexed(source);
+        let greater_equal = &result.tokens[1];
+        let underlined = result.underline(source, greater_equal.span()).unwrap();
+
+        assert_eq!(underlined, "x >= 1.5\n  ^~");
+        let marks = underlined.lines().nth(1).unwrap().trim();
+        assert_eq!(marks.len() as u64, greater_equal.end.1 - greater_equal.position.1);
+    }
+
+    #[test]
+    fn underlines_the_first_line_of_a_long_token() {
+        let source = "var s = \"one\ntwo\";";
+        let result = scan_indexed(source);
+
+        assert_eq!(result.underline(source, result.tokens[3].span()).unwrap(), "var s = \"one\n        ^~~~...");
+    }
+
+    #[test]
+    fn underlines_where_a_token_with_nothing_in_it_would_be() {
+        let source = "x >= 1.5";
+        let result = scan_indexed(source);
+        let eof = result.tokens.last().unwrap();
+
+        assert_eq!(result.underline(source, eof.span()).unwrap(), "x >= 1.5\n        ^");
+    }
+
+    #[test]
+    fn underlines_after_wide_tabs() {
+        let source = "\tx >= 1";
+        let result = ScannerBuilder::new().tab_width(4).build().scan_indexed(source);
+
+        assert_eq!(result.tokens[1].position, (1, 7));
+        assert_eq!(result.underline(source, result.tokens[1].span()).unwrap(), "\tx >= 1\n\t  ^~");
+    }
+}
@@ -1,4 +1,5 @@
 use std::fmt::{Display,Formatter,Result as FResult};
+use std::sync::Arc;
 
 use token_type::Type;
 use literal::Literal;
@@ -6,34 +7,182 @@ use literal::Literal;
 type Line = u64;
 type Column = u64;
 type Position = (Line, Column);
+// where a token starts, and where the next character after it would be
+type Span = (Position, Position);
 
-type Lexeme = String;
+// shared, so that a scan can hand out one copy of a name
+// for every place it's used
+type Lexeme = Arc<str>;
 
+// equal tokens were also found in the same place;
+// see eq_ignoring_position for comparing just what they say
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub token_type: Type,
     pub lexeme: Lexeme,
     pub literal: Option<Literal>,
     pub position: Position,
+    // just past the last character, so `position..end` is the whole lexeme
+    pub end: Position,
+    // the same extent as byte offsets into the source, for tools that edit text
+    pub offsets: (usize, usize),
+    // true for tokens that were made up rather than read out of the source
+    // (think a virtual eof or an implied semicolon), so tooling knows
+    // there's nothing at `position` to point at.
+    pub synthetic: bool,
+    // the type tag on a number like `255u8`, if suffixes are enabled
+    pub suffix: Option<String>,
 }
 
 impl Token {
-    pub fn new(tt: Type, lex: Lexeme, lit: Option<Literal>, pos: Position) -> Self {
+    pub fn new<L: Into<Lexeme>>(tt: Type, lex: L, lit: Option<Literal>, span: Span) -> Self {
         Token {
             token_type: tt,
-            lexeme: lex,
+            lexeme: lex.into(),
             literal: lit,
-            position: pos,
+            position: span.0,
+            end: span.1,
+            offsets: (0, 0),
+            synthetic: false,
+            suffix: None,
         }
     }
+
+    pub fn synthetic<L: Into<Lexeme>>(tt: Type, lex: L, pos: Position) -> Self {
+        Token {
+            synthetic: true,
+            ..Token::new(tt, lex, None, (pos, pos))
+        }
+    }
+
+    // whether this is the first token on its line, given the one before it.
+    // that's about where the one before ends, which for a string
+    // with line breaks in it isn't the line it started on.
+    pub fn starts_line(&self, previous: Option<&Token>) -> bool {
+        previous
+            .map(|p| p.end.0 != self.position.0)
+            .unwrap_or(true)
+    }
+
+    pub fn span(&self) -> Span {
+        (self.position, self.end)
+    }
+
+    pub fn is_keyword(&self) -> bool {
+        self.token_type.is_keyword()
+    }
+
+    pub fn is_literal(&self) -> bool {
+        self.token_type.is_literal()
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.token_type.is_operator()
+    }
+
+    // what a token says, without where it was, for keying maps and sets.
+    // the literal is left out: it comes from the type and lexeme anyway,
+    // and an f64 has no sensible hash.
+    pub fn key(&self) -> (Type, &str) {
+        (self.token_type, &self.lexeme)
+    }
+
+    pub fn eq_ignoring_position(&self, other: &Token) -> bool {
+        self.token_type == other.token_type
+            && self.lexeme == other.lexeme
+            && self.literal == other.literal
+            && self.suffix == other.suffix
+    }
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter) -> FResult {
         match self.literal {
-            None => write!(f, "<Token type: {:?}, lexeme: {:?}, position: ({}, {})>",
-                                self.token_type, self.lexeme, self.position.0, self.position.1),
-            Some(ref lit) => write!(f, "<Token type: {:?}, lexeme: {:?}, literal: {:?}, position: ({}, {})>",
-                                self.token_type, self.lexeme, lit, self.position.0, self.position.1),
+            None => write!(f, "<Token type: {:?}, lexeme: {:?}, span: ({}, {})..({}, {})>",
+                                self.token_type, self.lexeme,
+                                self.position.0, self.position.1, self.end.0, self.end.1),
+            Some(ref lit) => write!(f, "<Token type: {:?}, lexeme: {:?}, literal: {:?}, span: ({}, {})..({}, {})>",
+                                self.token_type, self.lexeme, lit,
+                                self.position.0, self.position.1, self.end.0, self.end.1),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scanner::scan;
+
+    #[test]
+    fn scanned_tokens_are_not_synthetic() {
+        let tokens = scan("var x = 1;").unwrap();
+        let (eof, scanned) = tokens.split_last().unwrap();
+
+        assert!(scanned.iter().all(|t| !t.synthetic));
+        // nothing in the source marks its end
+        assert!(eof.synthetic);
+    }
+
+    #[test]
+    fn made_up_tokens_are_synthetic() {
+        let semicolon = Token::synthetic(Type::Semicolon, ";", (1, 5));
+
+        assert!(semicolon.synthetic);
+        assert_eq!(semicolon.span(), ((1, 5), (1, 5)));
+        assert!(!Token::new(Type::Semicolon, ";", None, ((1, 5), (1, 6))).synthetic);
+    }
+
+    #[test]
+    fn debug_shows_every_field() {
+        let tokens = scan("x >= 1.5").unwrap();
+        let debug: Vec<String> = tokens.iter().map(|t| format!("{:?}", t)).collect();
+
+        assert_eq!(debug, vec![
+            "Token { token_type: Identifier, lexeme: \"x\", literal: None, position: (1, 1), end: (1, 2), \
+             offsets: (0, 1), synthetic: false, suffix: None }",
+            "Token { token_type: GreaterEqual, lexeme: \">=\", literal: None, position: (1, 3), end: (1, 5), \
+             offsets: (2, 4), synthetic: false, suffix: None }",
+            "Token { token_type: Number, lexeme: \"1.5\", literal: Some(Number(1.5)), position: (1, 6), end: (1, 9), \
+             offsets: (5, 8), synthetic: false, suffix: None }",
+            "Token { token_type: Eof, lexeme: \"\", literal: None, position: (1, 9), end: (1, 9), \
+             offsets: (8, 8), synthetic: true, suffix: None }",
+        ]);
+    }
+
+    #[test]
+    fn tokens_that_start_lines() {
+        let tokens = scan("var a =\n  1;\nprint a; print a;\n").unwrap();
+        let starts: Vec<&str> = tokens.iter().enumerate()
+            .filter(|&(i, token)| token.starts_line(i.checked_sub(1).map(|p| &tokens[p])))
+            .map(|(_, token)| &*token.lexeme)
+            .collect();
+
+        assert_eq!(starts, vec!["var", "1", "print", ""]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tokens_round_trip_through_json() {
+        let tokens = scan("var x = \"hi\" + 2.5;").unwrap();
+        let json = serde_json::to_string(&tokens).unwrap();
+        let back: Vec<Token> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back, tokens);
+        // readable enough to diff by eye
+        assert!(json.contains("\"token_type\":\"Var\""));
+        assert!(json.contains("\"position\":[1,1]"));
+        assert!(json.contains("\"literal\":{\"type\":\"String\",\"value\":\"hi\"}"));
+    }
+
+    #[test]
+    fn tokens_after_a_multi_line_string_share_its_last_line() {
+        let tokens = scan("a \"x\ny\" b\nc").unwrap();
+        let starts: Vec<bool> = tokens.iter().enumerate()
+            .map(|(i, token)| token.starts_line(i.checked_sub(1).map(|p| &tokens[p])))
+            .collect();
+
+        // a, the string, b, c, eof
+        assert_eq!(starts, vec![true, false, false, true, false]);
+    }
+}
@@ -1,6 +1,7 @@
-use std::string::ToString;
+use std::fmt::{Display,Formatter,Result as FResult};
 
-#[derive(Debug,Clone,Copy)]
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     LeftParen,
     RightParen,
@@ -13,6 +14,7 @@ pub enum Type {
     Semicolon,
     Slash,
     Star,
+    Percent,
 
     Bang,
     BangEqual,
@@ -22,11 +24,20 @@ pub enum Type {
     GreaterEqual,
     Less,
     LessEqual,
+    QuestionQuestion,
 
     // lits
     Identifier,
     String,
+    // the pieces of an interpolated string like `"a ${b} c ${d} e"`:
+    // `"a ${` starts it, `} c ${` comes between expressions and `} e"` ends it
+    InterpStart,
+    InterpPart,
+    InterpEnd,
+    Char,
     Number,
+    // text outside the code delimiters of a template
+    RawText,
 
     // Keywords,
     And,
@@ -45,12 +56,117 @@ pub enum Type {
     True,
     Var,
     While,
+    // a keyword an embedder added to the reserved words, which lox itself
+    // doesn't have; the lexeme says which one it is
+    Keyword,
 
-    EOF,
+    // only scanned when asked for
+    Comment,
+    Whitespace,
+
+    Eof,
+}
+
+impl Type {
+    // tokens that can begin a statement, which makes them
+    // good places for a parser to resynchronize after an error
+    pub const STATEMENT_STARTS: &[Type] = &[
+        Type::Class,
+        Type::Fun,
+        Type::Var,
+        Type::For,
+        Type::If,
+        Type::While,
+        Type::Print,
+        Type::Return,
+    ];
+
+    pub const ASSIGNMENT_OPS: &[Type] = &[
+        Type::Equal,
+    ];
+
+    // tokens that stand for a value all by themselves.
+    // the pieces of an interpolated string only make one together.
+    pub const LITERALS: &[Type] = &[
+        Type::String,
+        Type::Char,
+        Type::Number,
+        Type::True,
+        Type::False,
+        Type::Nil,
+    ];
+
+    pub const COMPARISONS: &[Type] = &[
+        Type::Greater,
+        Type::GreaterEqual,
+        Type::Less,
+        Type::LessEqual,
+    ];
+
+    pub fn is_keyword(&self) -> bool {
+        *self == Type::Keyword || self.keyword_str().is_some()
+    }
+
+    // how lox spells the keyword, if this is one of its own
+    pub fn keyword_str(&self) -> Option<&'static str> {
+        let word = match *self {
+            Type::And => "and",
+            Type::Class => "class",
+            Type::Else => "else",
+            Type::False => "false",
+            Type::Fun => "fun",
+            Type::For => "for",
+            Type::If => "if",
+            Type::Nil => "nil",
+            Type::Or => "or",
+            Type::Print => "print",
+            Type::Return => "return",
+            Type::Super => "super",
+            Type::This => "this",
+            Type::True => "true",
+            Type::Var => "var",
+            Type::While => "while",
+            _ => return None,
+        };
+        Some(word)
+    }
+
+    pub fn is_literal(&self) -> bool {
+        Type::LITERALS.contains(self)
+    }
+
+    // `and` and `or` count, even though they're spelled as words
+    pub fn is_operator(&self) -> bool {
+        matches!(*self,
+                 Type::Minus | Type::Plus | Type::Slash | Type::Star | Type::Percent |
+                 Type::Bang | Type::BangEqual | Type::Equal | Type::EqualEqual |
+                 Type::Greater | Type::GreaterEqual | Type::Less | Type::LessEqual |
+                 Type::QuestionQuestion | Type::And | Type::Or)
+    }
+
+    // operators the parser reads as a Binary between two operands.
+    // `=` isn't one of them: assignment is its own thing.
+    // neither are `and` and `or` yet, which need parsing of their own.
+    pub fn is_binary_operator(&self) -> bool {
+        matches!(*self,
+                 // arithmetic
+                 Type::Minus | Type::Plus | Type::Slash | Type::Star | Type::Percent |
+                 // equality
+                 Type::BangEqual | Type::EqualEqual |
+                 // comparison
+                 Type::Greater | Type::GreaterEqual | Type::Less | Type::LessEqual |
+                 // nil coalescing
+                 Type::QuestionQuestion)
+    }
+
+    // operators that go in front of a single operand
+    pub fn is_unary_operator(&self) -> bool {
+        matches!(*self, Type::Bang | Type::Minus | Type::Plus)
+    }
 }
 
-impl ToString for Type {
-    fn to_string(&self) -> String {
-        format!("{:?}", *self)
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter) -> FResult {
+        write!(f, "{:?}", *self)
     }
 }
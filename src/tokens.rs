@@ -0,0 +1,49 @@
+use token::Token;
+use token_type::Type;
+
+// each token along with whatever comes right before and after it,
+// for passes that care about adjacency like `)(` or `} {`.
+pub fn token_windows(tokens: &[Token]) -> impl Iterator<Item = (Option<&Token>, &Token, Option<&Token>)> {
+    tokens.iter().enumerate().map(move |(i, token)| {
+        let previous = i.checked_sub(1).and_then(|p| tokens.get(p));
+        (previous, token, tokens.get(i + 1))
+    })
+}
+
+// the tokens that mean something to the grammar. comments and whitespace are
+// only scanned for tools that want the source back exactly, and line breaks
+// are just more whitespace.
+pub fn significant(tokens: &[Token]) -> impl Iterator<Item = &Token> {
+    tokens.iter().filter(|t| !matches!(t.token_type, Type::Comment | Type::Whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scanner::{scan, ScannerBuilder};
+
+    #[test]
+    fn windows_pair_each_token_with_its_neighbours() {
+        let tokens = scan(") (").unwrap();
+        let windows: Vec<_> = token_windows(&tokens)
+            .map(|(previous, token, next)| (previous.map(|t| &*t.lexeme), &*token.lexeme, next.map(|t| &*t.lexeme)))
+            .collect();
+
+        assert_eq!(windows, vec![
+            (None, ")", Some("(")),
+            (Some(")"), "(", Some("")),
+            (Some("("), "", None),
+        ]);
+    }
+
+    #[test]
+    fn significant_leaves_out_comments_and_whitespace() {
+        let context = ScannerBuilder::new().keep_comments(true).keep_whitespace(true).build();
+        let tokens = context.scan("var x // the x\n= /* one */ 1;").unwrap();
+        let significant: Vec<_> = significant(&tokens).map(|t| &*t.lexeme).collect();
+
+        assert!(tokens.iter().any(|t| t.token_type == Type::Comment));
+        assert!(tokens.iter().any(|t| t.token_type == Type::Whitespace));
+        assert_eq!(significant, vec!["var", "x", "=", "1", ";", ""]);
+    }
+}
@@ -0,0 +1,77 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// runs rox on `source` and hands back whatever it wrote to stderr
+fn run(name: &str, source: &str, args: &[&str]) -> String {
+    let path: PathBuf = env::temp_dir().join(format!("rox-cli-{}-{}.lox", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rox"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+
+    String::from_utf8(output.stderr).unwrap()
+}
+
+fn diagnostics(stderr: &str) -> Vec<&str> {
+    stderr.lines().filter(|l| l.starts_with("Error")).collect()
+}
+
+#[test]
+fn max_errors_reports_exactly_that_many() {
+    let stderr = run("exact", "@ #\nx", &["--max-errors", "2"]);
+
+    assert_eq!(diagnostics(&stderr), vec![
+        "Error: Error reading code at line 1, column 1: Unexpected character: \"@\"",
+        "Error: Error reading code at line 1, column 3: Unexpected character: \"#\"",
+    ]);
+}
+
+#[test]
+fn max_errors_stops_once_there_are_more() {
+    let stderr = run("more", "@ # $ ^\n", &["--max-errors", "2"]);
+    let diagnostics = diagnostics(&stderr);
+
+    assert_eq!(diagnostics.len(), 3);
+    assert!(diagnostics[..2].iter().all(|d| d.contains("Unexpected character")));
+    assert!(diagnostics[2].ends_with("too many errors; stopping"));
+}
+
+#[test]
+fn max_errors_of_zero_reports_everything() {
+    let stderr = run("unlimited", &"@ ".repeat(150), &["--max-errors", "0"]);
+
+    assert_eq!(diagnostics(&stderr).len(), 150);
+}
+
+#[test]
+fn max_errors_defaults_to_a_hundred() {
+    let stderr = run("default", &"@ ".repeat(150), &[]);
+    let diagnostics = diagnostics(&stderr);
+
+    assert_eq!(diagnostics.len(), 101);
+    assert!(diagnostics[100].ends_with("too many errors; stopping"));
+}
+
+#[test]
+fn the_prompt_stops_when_input_runs_out() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rox"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"1\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // the one line's tokens, then nothing more
+    assert_eq!(stdout.matches("type: Eof").count(), 1, "{}", stdout);
+}